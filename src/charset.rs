@@ -0,0 +1,95 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use regex::bytes::Regex;
+use std::sync::LazyLock;
+
+/// How many bytes are searched for a `<meta>` charset declaration.
+const PRESCAN_LEN: usize = 4096;
+
+/// `<meta charset="…">` as well as `<meta http-equiv="Content-Type"
+/// content="text/html; charset=…">`.
+static META_CHARSET: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)<meta\s[^>]*?charset\s*=\s*["']?\s*([a-z0-9_:.+-]+)"#).unwrap()
+});
+
+/// The charset parameter of a `Content-Type` header value.
+fn header_encoding(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        Encoding::for_label(value.trim().trim_matches(['"', '\'']).as_bytes())
+    })
+}
+
+fn meta_encoding(body: &[u8]) -> Option<&'static Encoding> {
+    let prefix = &body[..body.len().min(PRESCAN_LEN)];
+    let label = META_CHARSET.captures(prefix)?.get(1)?.as_bytes();
+    let encoding = Encoding::for_label(label)?;
+    // a page read as ASCII can't really be UTF-16
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        Some(UTF_8)
+    } else {
+        Some(encoding)
+    }
+}
+
+/// Decodes an HTML body, its encoding sniffed from its byte order mark, its
+/// `Content-Type` header, then its `<meta>` declaration. Undeclared bodies
+/// are UTF-8 when valid, windows-1252 otherwise.
+pub(crate) fn decode_html(body: &[u8], content_type: Option<&str>) -> (String, &'static Encoding) {
+    let encoding = Encoding::for_bom(body)
+        .map(|(encoding, _)| encoding)
+        .or_else(|| content_type.and_then(header_encoding))
+        .or_else(|| meta_encoding(body))
+        .unwrap_or_else(|| {
+            if std::str::from_utf8(body).is_ok() {
+                UTF_8
+            } else {
+                WINDOWS_1252
+            }
+        });
+
+    let (text, encoding, _) = encoding.decode(body);
+    (text.into_owned(), encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::SHIFT_JIS;
+
+    #[test]
+    fn prefers_bom_then_header_then_meta() {
+        let body = b"\xEF\xBB\xBF<meta charset=\"windows-1252\">caf\xC3\xA9";
+        let (text, encoding) = decode_html(body, Some("text/html; charset=shift_jis"));
+        assert_eq!(encoding, UTF_8);
+        assert!(text.ends_with("café"));
+
+        let body = b"<meta charset=\"utf-8\">caf\xE9";
+        let (text, encoding) = decode_html(body, Some("text/html; charset=ISO-8859-1"));
+        assert_eq!(encoding, WINDOWS_1252);
+        assert!(text.ends_with("café"));
+    }
+
+    #[test]
+    fn sniffs_meta_declarations() {
+        let (body, _, _) = SHIFT_JIS.encode("<meta charset='shift_jis'><p>日本語</p>");
+        let (text, encoding) = decode_html(&body, Some("text/html"));
+        assert_eq!(encoding, SHIFT_JIS);
+        assert!(text.contains("日本語"));
+
+        let body = b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1252\">\x93quoted\x94";
+        let (text, encoding) = decode_html(body, None);
+        assert_eq!(encoding, WINDOWS_1252);
+        assert!(text.ends_with("\u{201C}quoted\u{201D}"));
+    }
+
+    #[test]
+    fn falls_back_without_declaration() {
+        assert_eq!(decode_html("café".as_bytes(), None).1, UTF_8);
+        let (text, encoding) = decode_html(b"caf\xE9", None);
+        assert_eq!(encoding, WINDOWS_1252);
+        assert_eq!(text, "café");
+    }
+}
@@ -0,0 +1,207 @@
+use crate::Document;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// What happens to pages whose content duplicates an earlier page.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateAction {
+    /// Leave them out of the crawl results, their links are still followed.
+    #[default]
+    Drop,
+    /// Load them with the earlier page url in their `duplicate_of` metadata.
+    Tag,
+}
+
+/// Deduplication of pages by content, exact and near duplicates.
+#[derive(Clone, Copy, Debug)]
+pub struct DedupPolicy {
+    pub action: DuplicateAction,
+    /// The most SimHash bits near duplicates may differ by, `None` to only
+    /// catch exact duplicates.
+    pub max_distance: Option<u32>,
+}
+
+impl Default for DedupPolicy {
+    fn default() -> Self {
+        Self {
+            action: DuplicateAction::Drop,
+            max_distance: Some(3),
+        }
+    }
+}
+
+/// Words per SimHash feature.
+const SHINGLE_LEN: usize = 3;
+
+fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The SimHash fingerprint of `words`, over their word shingles.
+fn simhash(words: &[&str]) -> u64 {
+    let mut weights = [0i64; 64];
+    for shingle in words.windows(SHINGLE_LEN.min(words.len())) {
+        let feature = hash(shingle);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if feature & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |fingerprint, (bit, _)| fingerprint | 1 << bit)
+}
+
+struct Fingerprint {
+    exact: u64,
+    simhash: u64,
+    source: String,
+}
+
+/// Remembers the content of the crawled pages, to find the duplicate ones.
+pub(crate) struct Deduplicator {
+    action: DuplicateAction,
+    max_distance: Option<u32>,
+    pages: Vec<Fingerprint>,
+}
+
+impl Deduplicator {
+    pub(crate) fn new(policy: &DedupPolicy) -> Self {
+        Self {
+            action: policy.action,
+            max_distance: policy.max_distance,
+            pages: Vec::new(),
+        }
+    }
+
+    /// Whether `doc` stays in the crawl results, per the duplicate action.
+    /// Tagged duplicates get the source of the earlier page in their
+    /// `duplicate_of` metadata.
+    pub(crate) fn keeps(&mut self, doc: &mut Document) -> bool {
+        let Some(original) = self.duplicate_of(doc) else {
+            return true;
+        };
+        match self.action {
+            DuplicateAction::Drop => false,
+            DuplicateAction::Tag => {
+                doc.metadata.insert("duplicate_of", original);
+                true
+            }
+        }
+    }
+
+    /// The source of the page `doc` duplicates, `None` when it is the first
+    /// one with its content. Pages without content are never duplicates.
+    pub(crate) fn duplicate_of(&mut self, doc: &Document) -> Option<String> {
+        let lowercase = doc.page_content.to_lowercase();
+        let words: Vec<&str> = lowercase.split_whitespace().collect();
+        if words.is_empty() {
+            return None;
+        }
+
+        let exact = hash(&words);
+        let simhash = simhash(&words);
+        let original = self.pages.iter().find(|page| {
+            page.exact == exact
+                || self
+                    .max_distance
+                    .is_some_and(|max| (page.simhash ^ simhash).count_ones() <= max)
+        });
+        if let Some(original) = original {
+            return Some(original.source.clone());
+        }
+
+        self.pages.push(Fingerprint {
+            exact,
+            simhash,
+            source: doc
+                .metadata
+                .get_str("source")
+                .unwrap_or_default()
+                .to_string(),
+        });
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Metadata;
+
+    const ARTICLE: &str = "The quick brown fox jumps over the lazy dog while the farmer \
+        watches from the porch, sipping coffee and reading the morning paper about the \
+        weather, the harvest and the prices of grain at the market in the nearby town, \
+        where the fair opens next week with music, food stalls and a parade of tractors.";
+
+    fn document(source: &str, page_content: &str) -> Document {
+        let mut metadata = Metadata::new();
+        metadata.insert("source", source);
+        Document {
+            page_content: page_content.to_string(),
+            metadata,
+            structured_data: vec![],
+        }
+    }
+
+    #[test]
+    fn finds_exact_duplicates() {
+        let mut dedup = Deduplicator::new(&DedupPolicy {
+            max_distance: None,
+            ..Default::default()
+        });
+        assert_eq!(dedup.duplicate_of(&document("/a", ARTICLE)), None);
+        let print_view = format!("  {}\n", ARTICLE.to_uppercase());
+        assert_eq!(
+            dedup.duplicate_of(&document("/a?print=1", &print_view)),
+            Some("/a".to_string())
+        );
+        let edited = ARTICLE.replace("coffee", "tea");
+        assert_eq!(dedup.duplicate_of(&document("/b", &edited)), None);
+    }
+
+    #[test]
+    fn finds_near_duplicates() {
+        let mut dedup = Deduplicator::new(&DedupPolicy::default());
+        assert_eq!(dedup.duplicate_of(&document("/a", ARTICLE)), None);
+        let with_session = format!("{ARTICLE} Session 8f2a.");
+        assert_eq!(
+            dedup.duplicate_of(&document("/a;jsessionid=8f2a", &with_session)),
+            Some("/a".to_string())
+        );
+        assert_eq!(
+            dedup.duplicate_of(&document(
+                "/c",
+                "A completely different page about rust crawlers."
+            )),
+            None
+        );
+        assert_eq!(dedup.duplicate_of(&document("/empty", "")), None);
+        assert_eq!(dedup.duplicate_of(&document("/empty2", " ")), None);
+    }
+
+    #[test]
+    fn applies_duplicate_action() {
+        let mut dedup = Deduplicator::new(&DedupPolicy::default());
+        assert!(dedup.keeps(&mut document("/a", ARTICLE)));
+        assert!(!dedup.keeps(&mut document("/a?print=1", ARTICLE)));
+
+        let mut dedup = Deduplicator::new(&DedupPolicy {
+            action: DuplicateAction::Tag,
+            ..Default::default()
+        });
+        let mut original = document("/a", ARTICLE);
+        assert!(dedup.keeps(&mut original));
+        assert!(!original.metadata.contains_key("duplicate_of"));
+        let mut duplicate = document("/a?print=1", ARTICLE);
+        assert!(dedup.keeps(&mut duplicate));
+        assert_eq!(duplicate.metadata.get_str("duplicate_of"), Some("/a"));
+    }
+}
@@ -0,0 +1,61 @@
+use reqwest::StatusCode;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoaderError {
+    #[error("invalid url `{url}`: {source}")]
+    InvalidUrl {
+        url: String,
+        #[source]
+        source: url::ParseError,
+    },
+
+    #[error("request to `{url}` failed: {source}")]
+    Http {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("request to `{url}` timed out")]
+    Timeout { url: String },
+
+    #[error("`{url}` responded with status {status}")]
+    Status { url: String, status: StatusCode },
+
+    #[error("`{url}` has unsupported content type `{content_type}`")]
+    UnsupportedContentType { url: String, content_type: String },
+
+    #[error("`{url}` redirects to `{location}`, outside the crawl scope")]
+    RedirectOutOfScope { url: String, location: String },
+
+    #[error("`{url}` is disallowed by robots.txt")]
+    Disallowed { url: String },
+
+    #[error("failed to parse `{url}`: {message}")]
+    Parse { url: String, message: String },
+
+    #[error("invalid selector `{selector}`: {message}")]
+    InvalidSelector { selector: String, message: String },
+
+    #[error("invalid user agent `{user_agent}`: {source}")]
+    InvalidUserAgent {
+        user_agent: String,
+        #[source]
+        source: reqwest::Error,
+    },
+}
+
+impl LoaderError {
+    pub(crate) fn from_reqwest(url: &str, source: reqwest::Error) -> Self {
+        if source.is_timeout() {
+            LoaderError::Timeout {
+                url: url.to_string(),
+            }
+        } else {
+            LoaderError::Http {
+                url: url.to_string(),
+                source,
+            }
+        }
+    }
+}
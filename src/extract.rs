@@ -0,0 +1,224 @@
+use crate::LoaderError;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use std::{collections::HashSet, sync::LazyLock};
+
+/// Turns a fetched page into its `Document::page_content`. The page `url` is
+/// the one it came from, after redirects.
+pub trait ContentExtractor: Send + Sync {
+    fn extract(&self, raw_html: &str, url: &str) -> String;
+}
+
+impl<F> ContentExtractor for F
+where
+    F: Fn(&str, &str) -> String + Send + Sync,
+{
+    fn extract(&self, raw_html: &str, url: &str) -> String {
+        self(raw_html, url)
+    }
+}
+
+static WHITESPACE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+").unwrap());
+
+/// Collapses every run of whitespace into a single space.
+pub(crate) fn normalize_whitespace(text: &str) -> String {
+    WHITESPACE.replace_all(text, " ").into_owned()
+}
+
+/// Narrows pages down to their content selectors matches, without their
+/// exclude selectors matches, before they reach the extractor.
+pub(crate) struct SelectorFilter {
+    content: Vec<Selector>,
+    exclude: Vec<Selector>,
+}
+
+fn parse_selectors(selectors: &[String]) -> Result<Vec<Selector>, LoaderError> {
+    selectors
+        .iter()
+        .map(|selector| {
+            Selector::parse(selector).map_err(|err| LoaderError::InvalidSelector {
+                selector: selector.clone(),
+                message: err.to_string(),
+            })
+        })
+        .collect()
+}
+
+impl SelectorFilter {
+    pub(crate) fn new(content: &[String], exclude: &[String]) -> Result<Self, LoaderError> {
+        Ok(Self {
+            content: parse_selectors(content)?,
+            exclude: parse_selectors(exclude)?,
+        })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.content.is_empty() && self.exclude.is_empty()
+    }
+
+    pub(crate) fn apply(&self, raw_html: &str) -> String {
+        let mut document = Html::parse_document(raw_html);
+
+        let excluded: Vec<_> = self
+            .exclude
+            .iter()
+            .flat_map(|selector| document.select(selector).map(|element| element.id()))
+            .collect();
+        for id in excluded {
+            if let Some(mut node) = document.tree.get_mut(id) {
+                node.detach();
+            }
+        }
+
+        if self.content.is_empty() {
+            return document.html();
+        }
+
+        // matches in document order, leaving out the ones inside another match
+        let mut kept = HashSet::new();
+        let mut content = Vec::new();
+        for element in document
+            .root_element()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+        {
+            let inside_match = element
+                .ancestors()
+                .any(|ancestor| kept.contains(&ancestor.id()));
+            if !inside_match && self.content.iter().any(|s| s.matches(&element)) {
+                kept.insert(element.id());
+                content.push(element.html());
+            }
+        }
+
+        let head_selector = Selector::parse("head").unwrap();
+        let head = document
+            .select(&head_selector)
+            .next()
+            .map(|head| head.html())
+            .unwrap_or_default();
+        format!("<html>{head}<body>{}</body></html>", content.join("\n"))
+    }
+}
+
+/// Elements left out of the text collected by the built-in extractors.
+#[derive(Clone, Debug)]
+pub struct TextBlocklist {
+    pub tags: Vec<String>,
+    /// Attribute names, with the value they must have or `None` for any value.
+    pub attributes: Vec<(String, Option<String>)>,
+    pub selectors: Vec<Selector>,
+}
+
+impl Default for TextBlocklist {
+    fn default() -> Self {
+        Self {
+            tags: [
+                "script", "style", "noscript", "template", "svg", "canvas", "iframe",
+            ]
+            .map(String::from)
+            .to_vec(),
+            attributes: vec![
+                ("hidden".to_string(), None),
+                ("aria-hidden".to_string(), Some("true".to_string())),
+            ],
+            selectors: vec![Selector::parse(
+                r#"[style*="display:none"], [style*="display: none"], [style*="visibility:hidden"], [style*="visibility: hidden"]"#,
+            )
+            .unwrap()],
+        }
+    }
+}
+
+impl TextBlocklist {
+    pub(crate) fn blocks(&self, element: &ElementRef) -> bool {
+        let value = element.value();
+        self.tags.iter().any(|tag| tag == value.name())
+            || self
+                .attributes
+                .iter()
+                .any(|(name, expected)| match (value.attr(name), expected) {
+                    (Some(_), None) => true,
+                    (Some(actual), Some(expected)) => actual.eq_ignore_ascii_case(expected),
+                    (None, _) => false,
+                })
+            || self
+                .selectors
+                .iter()
+                .any(|selector| selector.matches(element))
+    }
+}
+
+/// The default extractor, all the text of `<body>` but the blocklisted
+/// elements, with whitespace collapsed.
+#[derive(Clone, Debug, Default)]
+pub struct BodyTextExtractor {
+    pub blocklist: TextBlocklist,
+}
+
+fn collect_text(element: &ElementRef, blocklist: &TextBlocklist, text: &mut Vec<String>) {
+    for node in element.children() {
+        if let Some(child) = ElementRef::wrap(node) {
+            if blocklist.blocks(&child) {
+                continue;
+            }
+            collect_text(&child, blocklist, text);
+        } else if node.value().is_text() {
+            text.push(node.value().as_text().unwrap().text.to_string());
+        }
+    }
+}
+
+impl ContentExtractor for BodyTextExtractor {
+    fn extract(&self, raw_html: &str, _url: &str) -> String {
+        let document = Html::parse_document(raw_html);
+        let body_selector = Selector::parse("body").unwrap();
+
+        let mut text = Vec::new();
+        for element in document.select(&body_selector) {
+            collect_text(&element, &self.blocklist, &mut text);
+        }
+
+        normalize_whitespace(&text.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html><body>
+        <style>body { color: red }</style>
+        <noscript>Enable JavaScript</noscript>
+        <template><p>Template</p></template>
+        <svg><text>Icon</text></svg>
+        <p>Visible</p>
+        <p hidden>Hidden</p>
+        <span aria-hidden="true">Decoration</span>
+        <div style="display: none">Collapsed</div>
+        <div class="cookie-banner">Cookies</div>
+        <script>alert("hi")</script>
+        </body></html>"#;
+
+    #[test]
+    fn skips_default_blocklist() {
+        let text = BodyTextExtractor::default().extract(PAGE, "https://example.com");
+        assert_eq!(text.trim(), "Visible Cookies");
+    }
+
+    #[test]
+    fn skips_configured_blocklist() {
+        let extractor = BodyTextExtractor {
+            blocklist: TextBlocklist {
+                tags: vec!["script".to_string(), "style".to_string()],
+                attributes: vec![],
+                selectors: vec![Selector::parse(".cookie-banner").unwrap()],
+            },
+        };
+        let text = extractor.extract(PAGE, "https://example.com");
+        assert_eq!(
+            text.trim(),
+            "Enable JavaScript Icon Visible Hidden Decoration Collapsed"
+        );
+    }
+}
@@ -1,162 +1,679 @@
-use regex::Regex;
-use reqwest::Client;
-use scraper::{ElementRef, Html, Selector};
+mod charset;
+mod dedup;
+mod error;
+mod extract;
+mod markdown;
+mod metadata;
+mod normalize;
+mod readability;
+mod retry;
+mod robots;
+mod scope;
+mod sitemap;
+mod structured;
+mod throttle;
+
+use dedup::Deduplicator;
+use extract::SelectorFilter;
+use futures::{stream, stream::FuturesUnordered, Stream, StreamExt};
+use reqwest::{
+    header::{CONTENT_TYPE, RETRY_AFTER},
+    Client, Method, StatusCode, Url,
+};
+use robots::RobotsTxt;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use sitemap::{Sitemap, SitemapEntry};
 use std::{
-    collections::{HashMap, HashSet},
-    error::Error,
+    collections::{HashMap, HashSet, VecDeque},
     future::Future,
-    pin::Pin,
-    time::Duration,
+    pin::{pin, Pin},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
+use throttle::HostThrottle;
+use tokio::sync::OnceCell;
 
-#[derive(Debug)]
+pub use dedup::{DedupPolicy, DuplicateAction};
+pub use error::LoaderError;
+pub use extract::{BodyTextExtractor, ContentExtractor, TextBlocklist};
+pub use markdown::MarkdownExtractor;
+pub use metadata::Metadata;
+pub use normalize::UrlNormalizer;
+pub use readability::MainContentExtractor;
+pub use retry::RetryPolicy;
+pub use scope::CrawlScope;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Document {
     pub page_content: String,
-    pub metadata: HashMap<String, String>,
+    pub metadata: Metadata,
+    /// The JSON-LD and microdata items of the page.
+    pub structured_data: Vec<serde_json::Value>,
 }
 
+#[derive(Default)]
 pub struct RecursiveWebLoaderOptions {
+    pub extractor: Option<Box<dyn ContentExtractor>>,
+    /// Only the elements matching one of these selectors reach the extractor.
+    pub content_selectors: Option<Vec<String>>,
+    /// The elements matching one of these selectors never reach the extractor.
+    pub exclude_selectors: Option<Vec<String>>,
+    /// Extra meta tags to record, from their `name` or `property`, lowercased,
+    /// to their metadata key.
+    pub custom_meta: Option<HashMap<String, String>>,
     pub exclude_dirs: Option<Vec<String>>,
     pub max_depth: Option<usize>,
     pub timeout: Option<u64>,
+    /// Shorthand for `scope`, `CrawlScope::PathPrefix` when `true`,
+    /// `CrawlScope::Any` when `false`.
     pub prevent_outside: Option<bool>,
+    /// Takes precedence over `prevent_outside`.
+    pub scope: Option<CrawlScope>,
+    pub max_concurrency: Option<usize>,
+    pub follow_policy: Option<FollowPolicy>,
+    pub user_agent: Option<String>,
+    pub respect_robots_txt: Option<bool>,
+    pub sitemap_mode: Option<SitemapMode>,
+    pub requests_per_second: Option<f64>,
+    pub min_delay: Option<u64>,
+    /// The longest, in milliseconds, a host is held when it asks to back off
+    /// with `Retry-After`, one minute by default.
+    pub max_delay: Option<u64>,
+    /// The longest, in milliseconds, a robots.txt `Crawl-delay` spaces the
+    /// requests to a host, thirty seconds by default.
+    pub max_crawl_delay: Option<u64>,
+    pub retry_policy: Option<RetryPolicy>,
+    pub status_policy: Option<StatusPolicy>,
+    pub allowed_mime_types: Option<Vec<String>>,
+    pub head_requests: Option<bool>,
+    pub url_normalizer: Option<UrlNormalizer>,
+    /// Deduplicates pages by content, disabled when `None`.
+    pub dedup_policy: Option<DedupPolicy>,
+    pub canonical_policy: Option<CanonicalPolicy>,
+}
+
+/// Decides which crawled pages get their links followed, the root page is
+/// always followed.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum FollowPolicy {
+    /// Only follow links from urls ending with `/`.
+    #[default]
+    DirectoriesOnly,
+    /// Follow links from every fetched page.
+    AllHtml,
+    /// Follow links from the urls the function returns `true` for.
+    Custom(fn(&str) -> bool),
 }
 
-impl Default for RecursiveWebLoaderOptions {
-    fn default() -> Self {
-        Self {
-            exclude_dirs: None,
-            max_depth: None,
-            timeout: None,
-            prevent_outside: None,
+impl FollowPolicy {
+    fn follows(&self, url: &str) -> bool {
+        match self {
+            FollowPolicy::DirectoriesOnly => url.ends_with('/'),
+            FollowPolicy::AllHtml => true,
+            FollowPolicy::Custom(follows) => follows(url),
         }
     }
 }
 
+/// How sitemaps, listed in robots.txt or at `/sitemap.xml`, are used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SitemapMode {
+    #[default]
+    Disabled,
+    /// Crawl from the sitemap urls as well as from the root page.
+    Seed,
+    /// Only load the sitemap urls, without following any link. The crawl
+    /// fails when none of the sitemaps can be loaded.
+    Only,
+}
+
+/// What to do with pages answering with a non-success status. The root page
+/// failing is always reported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StatusPolicy {
+    /// Yield a `LoaderError::Status` for the page.
+    #[default]
+    Report,
+    /// Silently leave the page out.
+    Skip,
+    /// Load the page like any other, its status is in the `status_code`
+    /// metadata.
+    Index,
+}
+
+/// How pages declaring a `<link rel="canonical">` url are handled. Unless
+/// ignored, the canonical url is marked visited and pages sharing a canonical
+/// url are only loaded once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CanonicalPolicy {
+    /// The canonical url is the page `source`, the url it was fetched from is
+    /// in the `fetched_url` metadata.
+    #[default]
+    Source,
+    /// The page `source` is the url it was fetched from, the canonical url is
+    /// in the `canonical` metadata.
+    Separate,
+    /// Canonical urls are only recorded in the `canonical` metadata.
+    Ignore,
+}
+
+/// A fetched page response, whatever its status.
+struct FetchedResponse {
+    status: StatusCode,
+    /// The url the response came from, after redirects.
+    url: String,
+    /// The `Content-Type` header.
+    content_type: Option<String>,
+    /// The decoded body.
+    body: String,
+    encoding: &'static encoding_rs::Encoding,
+}
+
+struct FrontierEntry {
+    url: String,
+    depth: usize,
+    lastmod: Option<String>,
+}
+
+struct CrawledPage {
+    root: bool,
+    retries: usize,
+    result: Option<Result<Document, LoaderError>>,
+    children: Vec<FrontierEntry>,
+}
+
+struct CrawlEvent {
+    root: bool,
+    retries: usize,
+    result: Result<Document, LoaderError>,
+}
+
+/// Summary of a crawl, see `RecursiveWebLoader::load_with_report`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CrawlReport {
+    pub pages_loaded: usize,
+    pub pages_failed: usize,
+    pub retries: usize,
+}
+
+struct CrawlState<'a> {
+    frontier: VecDeque<FrontierEntry>,
+    visited: HashSet<String>,
+    in_flight: FuturesUnordered<Pin<Box<dyn Future<Output = CrawledPage> + Send + 'a>>>,
+    dedup: Option<Deduplicator>,
+    /// The visit keys of the loaded pages canonical urls, or of their urls.
+    loaded: HashSet<String>,
+    /// The sitemap `lastmod` of the pages, by visit key.
+    lastmods: HashMap<String, String>,
+}
+
 pub struct RecursiveWebLoader {
     url: String,
     exclude_dirs: Vec<String>,
     max_depth: usize,
     timeout: u64,
-    prevent_outside: bool,
+    /// The url the crawl scope is measured from, the root url once it is
+    /// fetched, after its redirects.
+    scope_root: std::sync::RwLock<Option<Url>>,
+    scope: CrawlScope,
+    max_concurrency: usize,
+    follow_policy: FollowPolicy,
+    user_agent: String,
+    respect_robots_txt: bool,
+    max_crawl_delay: Duration,
+    sitemap_mode: SitemapMode,
+    robots: std::sync::Mutex<HashMap<String, Arc<OnceCell<Arc<RobotsTxt>>>>>,
+    throttle: HostThrottle,
+    retry_policy: Option<RetryPolicy>,
+    status_policy: StatusPolicy,
+    allowed_mime_types: Vec<String>,
+    head_requests: bool,
+    url_normalizer: UrlNormalizer,
+    dedup_policy: Option<DedupPolicy>,
+    canonical_policy: CanonicalPolicy,
+    extractor: Box<dyn ContentExtractor>,
+    selector_filter: SelectorFilter,
+    custom_meta: HashMap<String, String>,
     client: Client,
 }
 
-fn collect_text_not_in_script(element: &ElementRef, text: &mut Vec<String>) {
-    for node in element.children() {
-        if node.value().is_element() {
-            let tag_name = node.value().as_element().unwrap().name();
-            if tag_name == "script" {
-                continue;
-            }
-            collect_text_not_in_script(&ElementRef::wrap(node).unwrap(), text);
-        } else if node.value().is_text() {
-            text.push(node.value().as_text().unwrap().text.to_string());
-        }
+/// The most sitemaps fetched for a crawl, indexes included.
+const MAX_SITEMAPS: usize = 100;
+
+fn content_type(response: &reqwest::Response) -> Option<&str> {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// The part of `url` robots.txt rules are matched against.
+fn robots_path(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
     }
 }
 
 impl RecursiveWebLoader {
-    pub fn new(url: String, options: RecursiveWebLoaderOptions) -> Self {
-        Self {
+    /// Fails when one of the content or exclude selectors is invalid.
+    pub fn new(url: String, options: RecursiveWebLoaderOptions) -> Result<Self, LoaderError> {
+        let selector_filter = SelectorFilter::new(
+            &options.content_selectors.unwrap_or_default(),
+            &options.exclude_selectors.unwrap_or_default(),
+        )?;
+        let user_agent = options.user_agent.unwrap_or_else(|| {
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).to_string()
+        });
+        let client = Client::builder()
+            .user_agent(&user_agent)
+            .build()
+            .map_err(|source| LoaderError::InvalidUserAgent {
+                user_agent: user_agent.clone(),
+                source,
+            })?;
+
+        let mut min_interval = Duration::from_millis(options.min_delay.unwrap_or(0));
+        if let Some(rps) = options.requests_per_second.filter(|rps| *rps > 0.0) {
+            min_interval = min_interval.max(Duration::from_secs_f64(1.0 / rps));
+        }
+
+        let scope_root = std::sync::RwLock::new(Url::parse(&url).ok());
+        Ok(Self {
             url,
             exclude_dirs: options.exclude_dirs.unwrap_or_default(),
             max_depth: options.max_depth.unwrap_or(2),
             timeout: options.timeout.unwrap_or(10000),
-            prevent_outside: options.prevent_outside.unwrap_or(true),
-            client: Client::new(),
-        }
+            scope_root,
+            scope: options
+                .scope
+                .unwrap_or(match options.prevent_outside.unwrap_or(true) {
+                    true => CrawlScope::PathPrefix,
+                    false => CrawlScope::Any,
+                }),
+            max_concurrency: options.max_concurrency.unwrap_or(4).max(1),
+            follow_policy: options.follow_policy.unwrap_or_default(),
+            user_agent,
+            respect_robots_txt: options.respect_robots_txt.unwrap_or(true),
+            max_crawl_delay: Duration::from_millis(options.max_crawl_delay.unwrap_or(30_000)),
+            sitemap_mode: options.sitemap_mode.unwrap_or_default(),
+            robots: std::sync::Mutex::new(HashMap::new()),
+            throttle: HostThrottle::new(
+                min_interval,
+                Duration::from_millis(options.max_delay.unwrap_or(60_000)),
+            ),
+            retry_policy: options.retry_policy,
+            status_policy: options.status_policy.unwrap_or_default(),
+            allowed_mime_types: options
+                .allowed_mime_types
+                .unwrap_or_else(|| vec!["text/html".into(), "application/xhtml+xml".into()])
+                .iter()
+                .map(|mime_type| mime_type.to_lowercase())
+                .collect(),
+            head_requests: options.head_requests.unwrap_or(false),
+            url_normalizer: options.url_normalizer.unwrap_or_default(),
+            dedup_policy: options.dedup_policy,
+            canonical_policy: options.canonical_policy.unwrap_or_default(),
+            extractor: options
+                .extractor
+                .unwrap_or_else(|| Box::new(BodyTextExtractor::default())),
+            selector_filter,
+            custom_meta: options
+                .custom_meta
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, key)| (name.to_lowercase(), key))
+                .collect(),
+            client,
+        })
     }
 
-    async fn fetch_url(&self, url: &str) -> Result<String, Box<dyn Error>> {
-        Ok(self
+    /// Sends a `method` request to `url`, backing off its host when told to.
+    async fn send_request(
+        &self,
+        method: Method,
+        url: &str,
+    ) -> Result<reqwest::Response, LoaderError> {
+        let response = self
             .client
-            .get(url)
+            .request(method, url)
             .timeout(Duration::from_millis(self.timeout))
             .send()
-            .await?
-            .text()
-            .await?)
+            .await
+            .map_err(|err| LoaderError::from_reqwest(url, err))?;
+
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(throttle::parse_retry_after);
+            if let (Some(host), Some(retry_after)) = (response.url().host_str(), retry_after) {
+                self.throttle.back_off(host, retry_after);
+            }
+        }
+
+        Ok(response)
+    }
+
+    async fn send(&self, url: &str) -> Result<reqwest::Response, LoaderError> {
+        let response = self.send_request(Method::GET, url).await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(LoaderError::Status {
+                url: url.to_string(),
+                status,
+            });
+        }
+
+        Ok(response)
+    }
+
+    /// Fails when `content_type` is not allowed. Responses without any are
+    /// assumed to be HTML.
+    fn check_content_type(&self, url: &str, content_type: Option<&str>) -> Result<(), LoaderError> {
+        let Some(content_type) = content_type else {
+            return Ok(());
+        };
+
+        let mime_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        if self.allowed_mime_types.contains(&mime_type) {
+            Ok(())
+        } else {
+            Err(LoaderError::UnsupportedContentType {
+                url: url.to_string(),
+                content_type: mime_type,
+            })
+        }
     }
 
-    fn extract_metadata(&self, raw_html: &str, url: &str) -> HashMap<String, String> {
-        let mut metadata = HashMap::new();
-        metadata.insert("source".to_string(), url.to_string());
+    /// Fetches a page, whatever the status it answers with, as long as the
+    /// content type of a success is allowed. Each request waits for the turn
+    /// of its host.
+    async fn fetch_url(&self, page_url: &Url) -> Result<FetchedResponse, LoaderError> {
+        let url = page_url.as_str();
+        if self.head_requests {
+            self.wait_for_turn(page_url).await;
+            let response = self.send_request(Method::HEAD, url).await?;
+            if response.status().is_success() {
+                self.check_content_type(url, content_type(&response))?;
+            }
+        }
 
-        let document = Html::parse_document(raw_html);
-        let title_selector = Selector::parse("title").unwrap();
-        if let Some(title) = document.select(&title_selector).next() {
-            metadata.insert("title".to_string(), title.inner_html());
+        self.wait_for_turn(page_url).await;
+        let response = self.send_request(Method::GET, url).await?;
+        // error pages are left to the retry and status policies
+        let status = response.status();
+        if status.is_success() {
+            self.check_content_type(url, content_type(&response))?;
         }
+        let final_url = response.url().to_string();
+        let content_type = content_type(&response).map(str::to_string);
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| LoaderError::from_reqwest(url, err))?;
+        let (body, encoding) = charset::decode_html(&bytes, content_type.as_deref());
+
+        Ok(FetchedResponse {
+            status,
+            url: final_url,
+            content_type,
+            body,
+            encoding,
+        })
+    }
 
-        let description_selector = Selector::parse("meta[name=description]").unwrap();
-        if let Some(description) = document.select(&description_selector).next() {
-            if let Some(content) = description.value().attr("content") {
-                metadata.insert("description".to_string(), content.to_string());
+    /// Fetches `url`, in its host turn, retrying per the retry policy. Adds
+    /// the retries made to `retries`.
+    async fn fetch_with_retries(
+        &self,
+        url: &Url,
+        retries: &mut usize,
+    ) -> Result<FetchedResponse, LoaderError> {
+        let mut attempt = 1;
+        loop {
+            let result = self.fetch_url(url).await;
+
+            let Some(policy) = self.retry_policy.as_ref() else {
+                return result;
+            };
+            let retryable = match &result {
+                Ok(response) => {
+                    !response.status.is_success()
+                        && policy.retryable_statuses.contains(&response.status)
+                }
+                Err(err) => policy.is_retryable(err),
+            };
+            if !retryable || attempt >= policy.max_attempts {
+                return result;
             }
+
+            tokio::time::sleep(policy.backoff(attempt - 1)).await;
+            attempt += 1;
+            *retries += 1;
         }
+    }
 
-        let html_selector = Selector::parse("html").unwrap();
-        if let Some(html) = document.select(&html_selector).next() {
-            if let Some(lang) = html.value().attr("lang") {
-                metadata.insert("language".to_string(), lang.to_string());
+    /// Fetches the body of `url`, failing past `max_size` bytes, or cut to
+    /// `max_size` bytes when `truncate`.
+    async fn fetch_bytes(
+        &self,
+        url: &str,
+        max_size: usize,
+        truncate: bool,
+    ) -> Result<Vec<u8>, LoaderError> {
+        let mut response = self.send(url).await?;
+        let mut body = vec![];
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|err| LoaderError::from_reqwest(url, err))?
+        {
+            if body.len() + chunk.len() > max_size {
+                if truncate {
+                    body.extend_from_slice(&chunk[..max_size - body.len()]);
+                    break;
+                }
+                return Err(LoaderError::Parse {
+                    url: url.to_string(),
+                    message: format!("body larger than {max_size} bytes"),
+                });
             }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+
+    /// Returns the robots.txt of the origin of `url`, fetching it on first use.
+    /// `None` when robots.txt support is disabled.
+    async fn robots_for(&self, url: &Url) -> Option<Arc<RobotsTxt>> {
+        if !self.respect_robots_txt {
+            return None;
         }
 
-        metadata
+        // fetched once per origin, without holding up the other origins
+        let origin = url.origin().ascii_serialization();
+        let cell = self
+            .robots
+            .lock()
+            .unwrap()
+            .entry(origin.clone())
+            .or_default()
+            .clone();
+        let origin_robots = cell
+            .get_or_init(|| async {
+                let robots_url = format!("{origin}/robots.txt");
+                let robots = match self.fetch_bytes(&robots_url, robots::MAX_SIZE, true).await {
+                    Ok(content) => RobotsTxt::parse(&String::from_utf8_lossy(&content)),
+                    // a missing robots.txt allows everything, an unreachable
+                    // one disallows everything (RFC 9309 2.3.1)
+                    Err(LoaderError::Status { status, .. })
+                        if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS =>
+                    {
+                        RobotsTxt::default()
+                    }
+                    Err(_) => RobotsTxt::disallow_all(),
+                };
+                Arc::new(robots)
+            })
+            .await;
+        Some(origin_robots.clone())
     }
 
-    fn extractor(&self, raw_html: &str) -> String {
-        let document = Html::parse_document(raw_html);
-        let body_selector = Selector::parse("body").unwrap();
+    async fn is_allowed_by_robots(&self, url: &Url) -> bool {
+        match self.robots_for(url).await {
+            Some(robots) => robots.is_allowed(&self.user_agent, &robots_path(url)),
+            None => true,
+        }
+    }
 
-        let mut text = Vec::new();
-        for element in document.select(&body_selector) {
-            collect_text_not_in_script(&element, &mut text);
+    async fn retain_allowed_by_robots(&self, urls: Vec<String>) -> Vec<String> {
+        let mut allowed = vec![];
+        for url in urls {
+            let Ok(parsed) = Url::parse(&url) else {
+                continue;
+            };
+            if self.is_allowed_by_robots(&parsed).await {
+                allowed.push(url);
+            }
         }
+        allowed
+    }
 
-        let joined_text = text.join(" ");
-        let cleaned_text = joined_text.replace("\n", " ").replace("\t", " ");
-        let re = Regex::new(r"\s+").unwrap();
-        re.replace_all(&cleaned_text, " ").to_string()
+    /// Waits until the host of `url` can be fetched again, spacing fetches by
+    /// the throttle interval or its robots.txt `Crawl-delay`, up to the
+    /// maximum crawl delay, when longer.
+    async fn wait_for_turn(&self, url: &Url) {
+        let Some(host) = url.host_str() else {
+            return;
+        };
+
+        let crawl_delay = self
+            .robots_for(url)
+            .await
+            .and_then(|robots| robots.crawl_delay(&self.user_agent))
+            .unwrap_or_default()
+            .min(self.max_crawl_delay);
+        self.throttle.wait(host, crawl_delay).await;
     }
 
-    async fn get_url_as_doc(&self, url: &str) -> Option<Document> {
-        match self.fetch_url(url).await {
-            Ok(response) => {
-                let page_content = self.extractor(&response);
-                let metadata = self.extract_metadata(&response, url);
-                Some(Document {
-                    page_content,
-                    metadata,
-                })
+    /// Collects the entries of the sitemaps of the origin of `root`, most
+    /// important first. Sitemaps that fail to load are skipped, unless none
+    /// loads.
+    async fn sitemap_entries(&self, root: &Url) -> Result<Vec<SitemapEntry>, LoaderError> {
+        let mut pending: VecDeque<String> = match self.robots_for(root).await {
+            Some(robots) if !robots.sitemaps().is_empty() => {
+                robots.sitemaps().iter().cloned().collect()
+            }
+            _ => root
+                .join("/sitemap.xml")
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        };
+
+        // bounded, as indexes may list more sitemaps than worth fetching
+        let mut seen = HashSet::new();
+        let mut entries = vec![];
+        let mut loaded = false;
+        let mut last_err = None;
+        while let Some(sitemap_url) = pending.pop_front() {
+            if seen.len() >= MAX_SITEMAPS || entries.len() >= sitemap::MAX_URLS {
+                break;
+            }
+            if !seen.insert(sitemap_url.clone()) {
+                continue;
+            }
+            if let Ok(url) = Url::parse(&sitemap_url) {
+                self.wait_for_turn(&url).await;
+            }
+            let sitemap = match self
+                .fetch_bytes(&sitemap_url, sitemap::MAX_SIZE, false)
+                .await
+            {
+                Ok(body) => Sitemap::parse(&body).map_err(|message| LoaderError::Parse {
+                    url: sitemap_url.clone(),
+                    message,
+                }),
+                Err(err) => Err(err),
+            };
+            match sitemap {
+                Ok(Sitemap::UrlSet(urls)) => entries.extend(urls),
+                Ok(Sitemap::Index(sitemaps)) => pending.extend(sitemaps),
+                Err(err) => {
+                    last_err = Some(err);
+                    continue;
+                }
+            }
+            loaded = true;
+        }
+        if let (false, Some(err)) = (loaded, last_err) {
+            return Err(err);
+        }
+        entries.truncate(sitemap::MAX_URLS);
+
+        let mut allowed = vec![];
+        for entry in entries {
+            if self.is_excluded(&entry.loc) {
+                continue;
+            }
+            if let Ok(url) = Url::parse(&entry.loc) {
+                if self.in_scope(&url) && self.is_allowed_by_robots(&url).await {
+                    allowed.push(entry);
+                }
             }
-            Err(_) => None,
+        }
+
+        // sitemaps default to a priority of 0.5
+        allowed.sort_by(|a, b| {
+            b.priority
+                .unwrap_or(0.5)
+                .total_cmp(&a.priority.unwrap_or(0.5))
+        });
+        Ok(allowed)
+    }
+
+    fn build_document(&self, raw_html: &str, url: &str) -> Document {
+        let page_content = if self.selector_filter.is_empty() {
+            self.extractor.extract(raw_html, url)
+        } else {
+            self.extractor
+                .extract(&self.selector_filter.apply(raw_html), url)
+        };
+
+        Document {
+            page_content,
+            metadata: metadata::extract_metadata(raw_html, url, &self.custom_meta),
+            structured_data: structured::extract_structured_data(raw_html),
         }
     }
 
-    fn get_child_links(&self, html: &str, base_url: &str) -> Vec<String> {
+    fn get_child_links(&self, html: &str, base_url: &str) -> Result<Vec<String>, LoaderError> {
         let document = Html::parse_document(html);
         let selector = Selector::parse("a").unwrap();
-        let base_url = reqwest::Url::parse(base_url).unwrap();
+        let base_url = Url::parse(base_url).map_err(|err| LoaderError::Parse {
+            url: base_url.to_string(),
+            message: err.to_string(),
+        })?;
 
-        document
+        let links = document
             .select(&selector)
             .filter_map(|element| element.value().attr("href"))
-            .filter_map(|href| {
-                if href.starts_with("http") {
-                    Some(href.to_string())
-                } else if href.starts_with("//") {
-                    Some(format!("{}:{}", base_url.scheme(), href))
-                } else {
-                    base_url.join(href).ok().map(|url| url.to_string())
-                }
-            })
+            .filter_map(|href| base_url.join(href.trim()).ok())
+            .map(|url| self.url_normalizer.normalize(&url))
+            .filter(|url| self.in_scope(url))
+            .map(String::from)
             .filter(|link| {
-                !self
-                    .exclude_dirs
-                    .iter()
-                    .any(|ex_dir| link.starts_with(ex_dir))
+                !self.is_excluded(link)
                     && !link.starts_with("javascript:")
                     && !link.starts_with("mailto:")
                     && !link.ends_with(".css")
@@ -167,79 +684,390 @@ impl RecursiveWebLoader {
                     && !link.ends_with(".jpeg")
                     && !link.ends_with(".gif")
                     && !link.ends_with(".svg")
-                    && (!self.prevent_outside || link.starts_with(base_url.as_str()))
             })
-            .collect()
+            .collect();
+
+        Ok(links)
+    }
+
+    /// Whether `url` is in the crawl scope of the root url.
+    fn in_scope(&self, url: &Url) -> bool {
+        self.scope_root
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|root| self.scope.contains(root, url))
+    }
+
+    fn is_excluded(&self, url: &str) -> bool {
+        self.exclude_dirs
+            .iter()
+            .any(|ex_dir| url.starts_with(ex_dir))
+    }
+
+    /// The key of `url` in the visited set. A directory url keeps a key of
+    /// its own when only it gets its links followed, or its subtree would be
+    /// lost behind the url without the trailing slash.
+    fn visit_key(&self, url: &str) -> String {
+        let Ok(url) = Url::parse(url) else {
+            return url.to_string();
+        };
+        let key = self.url_normalizer.dedup_key(&url);
+        let directory = self.url_normalizer.normalize(&url);
+        if directory.path().ends_with('/')
+            && self.follow_policy.follows(directory.as_str())
+            && !self.follow_policy.follows(&key)
+        {
+            directory.to_string()
+        } else {
+            key
+        }
+    }
+
+    /// Pushes the unvisited `children` in front of the frontier, so the
+    /// crawl goes depth first in document order. Their sitemap `lastmod`s are
+    /// kept for whichever entry of the same page gets crawled.
+    fn enqueue_children(&self, state: &mut CrawlState, children: Vec<FrontierEntry>) {
+        for child in children.into_iter().rev() {
+            let key = self.visit_key(&child.url);
+            if state.visited.contains(&key) {
+                continue;
+            }
+            if let Some(lastmod) = &child.lastmod {
+                state.lastmods.insert(key, lastmod.clone());
+            }
+            state.frontier.push_front(child);
+        }
+    }
+
+    fn should_follow(&self, url: &str, depth: usize) -> bool {
+        depth < self.max_depth && (depth == 0 || self.follow_policy.follows(url))
     }
 
-    fn get_child_urls_recursive<'a>(
-        &'a self,
-        input_url: &'a str,
-        visited: &'a mut HashSet<String>,
+    /// Fetches `url` once and builds both its document and, when its links
+    /// are to be followed, its child links from the same response.
+    async fn fetch_page(
+        &self,
+        url: &str,
         depth: usize,
-    ) -> Pin<Box<dyn Future<Output = Vec<Document>> + Send + 'a>> {
-        Box::pin(async move {
-            if depth >= self.max_depth {
-                return vec![];
+        retries: &mut usize,
+    ) -> Result<(Document, Vec<String>), LoaderError> {
+        let page_url = Url::parse(url).map_err(|err| LoaderError::Parse {
+            url: url.to_string(),
+            message: err.to_string(),
+        })?;
+        if !self.is_allowed_by_robots(&page_url).await {
+            return Err(LoaderError::Disallowed {
+                url: url.to_string(),
+            });
+        }
+        let response = self.fetch_with_retries(&page_url, retries).await?;
+        if response.url != page_url.as_str() {
+            // redirects are held to the same rules, but the root may go anywhere
+            let final_url = Url::parse(&response.url).map_err(|err| LoaderError::Parse {
+                url: response.url.clone(),
+                message: err.to_string(),
+            })?;
+            if depth > 0 && !self.in_scope(&final_url) {
+                return Err(LoaderError::RedirectOutOfScope {
+                    url: url.to_string(),
+                    location: response.url,
+                });
+            }
+            if !self.is_allowed_by_robots(&final_url).await {
+                return Err(LoaderError::Disallowed { url: response.url });
+            }
+            // the scope follows the root, say from http to https
+            if depth == 0 {
+                *self.scope_root.write().unwrap() = Some(final_url);
+            }
+        }
+        if !response.status.is_success() {
+            if self.status_policy != StatusPolicy::Index {
+                return Err(LoaderError::Status {
+                    url: url.to_string(),
+                    status: response.status,
+                });
+            }
+            self.check_content_type(url, response.content_type.as_deref())?;
+        }
+
+        let mut child_urls = vec![];
+        // links are relative to the url the page came from after redirects
+        let base_url = response.url.clone();
+        if self.should_follow(url, depth) && !self.is_excluded(&base_url) {
+            child_urls = self
+                .get_child_links(&response.body, &base_url)
+                .unwrap_or_default();
+            child_urls = self.retain_allowed_by_robots(child_urls).await;
+        }
+
+        // like its links, the page urls are relative to its final url
+        let mut doc = self.build_document(&response.body, &response.url);
+        doc.metadata.insert("source", url);
+        doc.metadata.insert("status_code", response.status.as_u16());
+        doc.metadata.insert("final_url", response.url);
+        doc.metadata.insert("encoding", response.encoding.name());
+        doc.metadata.insert("depth", depth);
+        self.apply_canonical_policy(&mut doc);
+        if let Ok(fetched_at) = SystemTime::now().duration_since(UNIX_EPOCH) {
+            doc.metadata.insert("fetched_at", fetched_at.as_secs());
+        }
+        Ok((doc, child_urls))
+    }
+
+    async fn crawl_page(&self, entry: FrontierEntry) -> CrawledPage {
+        let mut retries = 0;
+        let fetched = self.fetch_page(&entry.url, entry.depth, &mut retries).await;
+        let (result, children) = match fetched {
+            Ok((mut doc, child_urls)) => {
+                if let Some(lastmod) = entry.lastmod {
+                    doc.metadata.insert("lastmod", lastmod);
+                }
+                let children = child_urls
+                    .into_iter()
+                    .map(|url| FrontierEntry {
+                        url,
+                        depth: entry.depth + 1,
+                        lastmod: None,
+                    })
+                    .collect();
+                (Ok(doc), children)
             }
+            Err(err) => (Err(err), vec![]),
+        };
+
+        CrawledPage {
+            root: false,
+            retries,
+            result: Some(result),
+            children,
+        }
+    }
 
-            let mut url = input_url.to_string();
-            if !input_url.ends_with('/') {
-                url.push('/');
+    async fn crawl_root(&self) -> CrawledPage {
+        let root_url = match Url::parse(&self.url) {
+            Ok(root_url) => root_url,
+            Err(source) => {
+                return CrawledPage {
+                    root: true,
+                    retries: 0,
+                    result: Some(Err(LoaderError::InvalidUrl {
+                        url: self.url.clone(),
+                        source,
+                    })),
+                    children: vec![],
+                }
             }
+        };
 
-            if self
-                .exclude_dirs
-                .iter()
-                .any(|ex_dir| url.starts_with(ex_dir))
-            {
-                return vec![];
+        let mut page = match self.sitemap_mode {
+            // the root page is not loaded, only used to find the sitemaps
+            SitemapMode::Only => CrawledPage {
+                root: true,
+                retries: 0,
+                result: match self.is_allowed_by_robots(&root_url).await {
+                    true => None,
+                    false => Some(Err(LoaderError::Disallowed {
+                        url: self.url.clone(),
+                    })),
+                },
+                children: vec![],
+            },
+            _ => {
+                let page = self
+                    .crawl_page(FrontierEntry {
+                        url: self.url.clone(),
+                        depth: 0,
+                        lastmod: None,
+                    })
+                    .await;
+                CrawledPage { root: true, ..page }
             }
+        };
 
-            let res = match self.fetch_url(&url).await {
-                Ok(res) => res,
-                Err(_) => return vec![],
+        let root_failed = matches!(page.result, Some(Err(_)));
+        if self.sitemap_mode != SitemapMode::Disabled && !root_failed {
+            // sitemap urls are leaves in `Only` mode
+            let depth = match self.sitemap_mode {
+                SitemapMode::Only => self.max_depth,
+                _ => 1,
             };
+            match self.sitemap_entries(&root_url).await {
+                Ok(entries) => {
+                    page.children
+                        .extend(entries.into_iter().map(|entry| FrontierEntry {
+                            url: match Url::parse(&entry.loc) {
+                                Ok(loc) => self.url_normalizer.normalize(&loc).to_string(),
+                                Err(_) => entry.loc,
+                            },
+                            depth,
+                            lastmod: entry.lastmod,
+                        }))
+                }
+                // without sitemaps, there is nothing to load
+                Err(err) if self.sitemap_mode == SitemapMode::Only => page.result = Some(Err(err)),
+                Err(_) => {}
+            }
+        }
 
-            let child_urls = self.get_child_links(&res, &url);
+        page
+    }
 
-            let mut results = vec![];
+    /// The canonical url of a page, unless it is out of the crawl scope.
+    fn scoped_canonical<'a>(&self, doc: &'a Document) -> Option<&'a str> {
+        doc.metadata
+            .get_str("canonical")
+            .filter(|canonical| Url::parse(canonical).is_ok_and(|url| self.in_scope(&url)))
+    }
 
-            for child_url in child_urls {
-                if visited.contains(&child_url) {
-                    continue;
-                }
-                visited.insert(child_url.clone());
+    /// Makes the canonical url of `doc`, when in scope, its source under
+    /// `CanonicalPolicy::Source`, the url it was fetched from going to its
+    /// `fetched_url` metadata.
+    fn apply_canonical_policy(&self, doc: &mut Document) {
+        if self.canonical_policy != CanonicalPolicy::Source {
+            return;
+        }
+        if let Some(canonical) = self.scoped_canonical(doc).map(str::to_string) {
+            if let Some(fetched_url) = doc.metadata.insert("source", canonical) {
+                doc.metadata.insert("fetched_url", fetched_url);
+            }
+        }
+    }
+
+    /// The visit key of the canonical url of a loaded page, or of the url it
+    /// came from when it has none in scope. `None` when canonical urls are
+    /// ignored.
+    fn canonical_key(&self, doc: &Document) -> Option<String> {
+        if self.canonical_policy == CanonicalPolicy::Ignore {
+            return None;
+        }
+        let url = self
+            .scoped_canonical(doc)
+            .or(doc.metadata.get_str("final_url"))
+            .or(doc.metadata.get_str("source"))?;
+        Some(self.visit_key(url))
+    }
+
+    /// Whether a page result, below the root, is left out of the crawl.
+    fn is_skipped(&self, result: &Option<Result<Document, LoaderError>>) -> bool {
+        match result {
+            Some(Err(LoaderError::Status { .. })) => self.status_policy == StatusPolicy::Skip,
+            Some(Err(
+                LoaderError::UnsupportedContentType { .. } | LoaderError::RedirectOutOfScope { .. },
+            )) => true,
+            _ => false,
+        }
+    }
 
-                if let Some(child_doc) = self.get_url_as_doc(&child_url).await {
-                    results.push(child_doc);
+    fn crawl(&self) -> impl Stream<Item = CrawlEvent> + '_ {
+        // the root page is left to the sitemaps in `Only` mode
+        let mut visited = HashSet::new();
+        if self.sitemap_mode != SitemapMode::Only {
+            visited.insert(self.visit_key(&self.url));
+        }
+        let state = CrawlState {
+            frontier: VecDeque::new(),
+            visited,
+            in_flight: FuturesUnordered::new(),
+            dedup: self.dedup_policy.as_ref().map(Deduplicator::new),
+            loaded: HashSet::new(),
+            lastmods: HashMap::new(),
+        };
+        state.in_flight.push(Box::pin(self.crawl_root()));
 
-                    if child_url.ends_with('/') {
-                        let mut child_docs = self
-                            .get_child_urls_recursive(&child_url, visited, depth + 1)
-                            .await;
-                        results.append(&mut child_docs);
+        stream::unfold(state, move |mut state| async move {
+            loop {
+                while state.in_flight.len() < self.max_concurrency {
+                    let Some(mut entry) = state.frontier.pop_front() else {
+                        break;
+                    };
+                    let key = self.visit_key(&entry.url);
+                    if entry.lastmod.is_none() {
+                        entry.lastmod = state.lastmods.get(&key).cloned();
+                    }
+                    if state.visited.insert(key) {
+                        state.in_flight.push(Box::pin(self.crawl_page(entry)));
                     }
                 }
-            }
 
-            results
+                let mut page = state.in_flight.next().await?;
+                // redirect targets are not fetched again
+                if let Some(Ok(doc)) = &page.result {
+                    if let Some(final_url) = doc.metadata.get_str("final_url") {
+                        state.visited.insert(self.visit_key(final_url));
+                    }
+                }
+                self.enqueue_children(&mut state, page.children);
+                if !page.root && self.is_skipped(&page.result) {
+                    continue;
+                }
+                let canonical_key = match &page.result {
+                    Some(Ok(doc)) => self.canonical_key(doc),
+                    _ => None,
+                };
+                if let Some(key) = canonical_key {
+                    // pages sharing a canonical url are only loaded once
+                    state.visited.insert(key.clone());
+                    if !state.loaded.insert(key) {
+                        continue;
+                    }
+                }
+                if let (Some(dedup), Some(Ok(doc))) = (&mut state.dedup, &mut page.result) {
+                    if !dedup.keeps(doc) {
+                        continue;
+                    }
+                }
+                if let Some(result) = page.result {
+                    let event = CrawlEvent {
+                        root: page.root,
+                        retries: page.retries,
+                        result,
+                    };
+                    return Some((event, state));
+                }
+            }
         })
     }
-    async fn load(&self) -> Vec<Document> {
-        let mut docs = vec![];
-        if let Some(root_doc) = self.get_url_as_doc(&self.url).await {
-            docs.push(root_doc);
 
-            let mut visited = HashSet::new();
-            visited.insert(self.url.clone());
+    /// Crawls the loader url and its children up to `max_depth`, yielding each
+    /// page as soon as it is fetched.
+    ///
+    /// Up to `max_concurrency` pages are fetched at once, so pages below the
+    /// root come in completion order. The root page always comes first, unless
+    /// `SitemapMode::Only` skips it. If it fails the stream yields that error
+    /// and ends, errors on pages below it are yielded and the crawl carries on.
+    pub fn stream(&self) -> impl Stream<Item = Result<Document, LoaderError>> + '_ {
+        self.crawl().map(|event| event.result)
+    }
+
+    /// Crawls the loader url and its children up to `max_depth`.
+    ///
+    /// Fails when the root page itself cannot be loaded, pages below it that
+    /// fail to load are skipped.
+    pub async fn load(&self) -> Result<Vec<Document>, LoaderError> {
+        Ok(self.load_with_report().await?.0)
+    }
 
-            let mut child_docs = self
-                .get_child_urls_recursive(&self.url, &mut visited, 0)
-                .await;
-            docs.append(&mut child_docs);
+    /// Same as `load`, also reporting how the crawl went.
+    pub async fn load_with_report(&self) -> Result<(Vec<Document>, CrawlReport), LoaderError> {
+        let mut docs = vec![];
+        let mut report = CrawlReport::default();
+        let mut crawl = pin!(self.crawl());
+
+        while let Some(event) = crawl.next().await {
+            report.retries += event.retries;
+            match event.result {
+                Ok(doc) => {
+                    report.pages_loaded += 1;
+                    docs.push(doc);
+                }
+                Err(err) if event.root => return Err(err),
+                Err(_) => report.pages_failed += 1,
+            }
         }
-        docs
+
+        Ok((docs, report))
     }
 }
 
@@ -260,30 +1088,1447 @@ mod tests {
     #[tokio::test]
     async fn new_recursive_web_loader() {
         // Request a new server from the pool
-        let mut server = mockito::Server::new_async().await;
+        let mut server = server_without_robots_txt().await;
 
         // Create a mock on the server
-        let mockRoot = server
+        let mock_root = server
             .mock("GET", "/")
             .with_status(200)
-            .with_header("content-type", "text/plain")
+            .with_header("content-type", "text/html")
             .with_body("<html><body>Hello World <a href=\"/sub\">foobarbaz</a></body></html>")
             .create();
 
-        let mockSubPath = server
+        let mock_sub_path = server
             .mock("GET", "/sub")
             .with_status(200)
-            .with_header("content-type", "text/plain")
+            .with_header("content-type", "text/html")
             .with_body("<html><body>Hi from sub path</body></html>")
             .create();
 
         let url = server.url();
-        let rwl = RecursiveWebLoader::new(url, RecursiveWebLoaderOptions::default());
+        let rwl = RecursiveWebLoader::new(url, RecursiveWebLoaderOptions::default()).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].page_content, "Hello World foobarbaz");
+        assert_eq!(result[1].page_content, "Hi from sub path");
+
+        mock_root.assert();
+        mock_sub_path.assert()
+    }
+
+    #[tokio::test]
+    async fn load_invalid_root_url() {
+        let rwl = RecursiveWebLoader::new(
+            "not a url".to_string(),
+            RecursiveWebLoaderOptions::default(),
+        )
+        .unwrap();
+        let result = rwl.load().await;
+        assert!(matches!(result, Err(LoaderError::InvalidUrl { .. })));
+    }
+
+    #[tokio::test]
+    async fn load_root_error_status() {
+        let mut server = server_without_robots_txt().await;
+        let mock_root = server.mock("GET", "/").with_status(503).create();
+
+        let rwl =
+            RecursiveWebLoader::new(server.url(), RecursiveWebLoaderOptions::default()).unwrap();
         let result = rwl.load().await;
-        println!("{:?}", result);
-        // assert_eq!(result[0], "Hello World");
+        assert!(matches!(
+            result,
+            Err(LoaderError::Status { status, .. }) if status.as_u16() == 503
+        ));
+
+        mock_root.assert();
+    }
+
+    #[tokio::test]
+    async fn load_skips_failing_children() {
+        let mut server = server_without_robots_txt().await;
+        let mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body("<html><body>Root <a href=\"/missing\">missing</a></body></html>")
+            .create();
+        let mock_missing = server.mock("GET", "/missing").with_status(404).create();
+
+        let rwl =
+            RecursiveWebLoader::new(server.url(), RecursiveWebLoaderOptions::default()).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 1);
+
+        mock_root.assert();
+        mock_missing.assert();
+    }
+
+    #[tokio::test]
+    async fn stream_yields_pages_and_child_errors() {
+        let mut server = server_without_robots_txt().await;
+        let mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body(
+                "<html><body>Root <a href=\"/missing\">missing</a> <a href=\"/sub\">sub</a></body></html>",
+            )
+            .create();
+        let mock_missing = server.mock("GET", "/missing").with_status(404).create();
+        let mock_sub_path = server
+            .mock("GET", "/sub")
+            .with_status(200)
+            .with_body("<html><body>Sub</body></html>")
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            max_concurrency: Some(1),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let results: Vec<_> = rwl.stream().collect().await;
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].as_ref().unwrap().page_content,
+            "Root missing sub"
+        );
+        assert!(matches!(results[1], Err(LoaderError::Status { .. })));
+        assert_eq!(results[2].as_ref().unwrap().page_content, "Sub");
+
+        mock_root.assert();
+        mock_missing.assert();
+        mock_sub_path.assert();
+    }
+
+    #[tokio::test]
+    async fn load_fetches_children_concurrently() {
+        let mut server = server_without_robots_txt().await;
+        let links: String = (0..8)
+            .map(|i| format!("<a href=\"/page{i}\">page {i}</a>"))
+            .collect();
+        let mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body(format!("<html><body>{links}</body></html>"))
+            .create();
+        let mock_pages = server
+            .mock("GET", mockito::Matcher::Regex(r"^/page\d$".to_string()))
+            .with_status(200)
+            .with_chunked_body(|w| {
+                std::thread::sleep(Duration::from_millis(200));
+                w.write_all(b"<html><body>Page</body></html>")
+            })
+            .expect(8)
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            max_concurrency: Some(8),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let start = std::time::Instant::now();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 9);
+        assert!(start.elapsed() < Duration::from_millis(8 * 200));
+
+        mock_root.assert();
+        mock_pages.assert();
+    }
+
+    #[tokio::test]
+    async fn load_fetches_directories_once() {
+        let mut server = server_without_robots_txt().await;
+        let mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body("<html><body>Root <a href=\"/docs/\">docs</a></body></html>")
+            .create();
+        let mock_docs = server
+            .mock("GET", "/docs/")
+            .with_status(200)
+            .with_body("<html><body>Docs <a href=\"/docs/intro\">intro</a></body></html>")
+            .expect(1)
+            .create();
+        let mock_intro = server
+            .mock("GET", "/docs/intro")
+            .with_status(200)
+            .with_body("<html><body>Intro</body></html>")
+            .create();
+
+        let rwl =
+            RecursiveWebLoader::new(server.url(), RecursiveWebLoaderOptions::default()).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 3);
+
+        mock_root.assert();
+        mock_docs.assert();
+        mock_intro.assert();
+    }
+
+    async fn mock_docs_without_slashes(server: &mut mockito::ServerGuard) -> Vec<mockito::Mock> {
+        vec![
+            server
+                .mock("GET", "/")
+                .with_status(200)
+                .with_body("<html><body>Root <a href=\"/docs/intro\">intro</a></body></html>")
+                .create_async()
+                .await,
+            server
+                .mock("GET", "/docs/intro")
+                .with_status(200)
+                .with_body("<html><body>Intro <a href=\"setup\">setup</a></body></html>")
+                .create_async()
+                .await,
+            server
+                .mock("GET", "/docs/setup")
+                .with_status(200)
+                .with_body("<html><body>Setup</body></html>")
+                .create_async()
+                .await,
+        ]
+    }
+
+    #[tokio::test]
+    async fn load_follows_directories_only_by_default() {
+        let mut server = server_without_robots_txt().await;
+        let _mocks = mock_docs_without_slashes(&mut server).await;
+
+        let rwl =
+            RecursiveWebLoader::new(server.url(), RecursiveWebLoaderOptions::default()).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn load_follows_all_html() {
+        let mut server = server_without_robots_txt().await;
+        let _mocks = mock_docs_without_slashes(&mut server).await;
 
-        mockRoot.assert();
-        mockSubPath.assert()
+        let options = RecursiveWebLoaderOptions {
+            follow_policy: Some(FollowPolicy::AllHtml),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 3);
+        assert!(result.iter().any(|doc| doc.page_content == "Setup"));
+    }
+
+    #[tokio::test]
+    async fn load_follows_custom_policy() {
+        let mut server = server_without_robots_txt().await;
+        let _mocks = mock_docs_without_slashes(&mut server).await;
+
+        let options = RecursiveWebLoaderOptions {
+            follow_policy: Some(FollowPolicy::Custom(|url| url.contains("/docs/"))),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 3);
+    }
+
+    /// A mock server answering 404 to robots.txt unless a test mocks it.
+    async fn server_without_robots_txt() -> mockito::ServerGuard {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/robots.txt")
+            .with_status(404)
+            .expect_at_least(0)
+            .create_async()
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn load_respects_robots_txt() {
+        let mut server = server_without_robots_txt().await;
+        let mock_robots = server
+            .mock("GET", "/robots.txt")
+            .with_status(200)
+            .with_body("User-agent: *\nDisallow: /private/\nCrawl-delay: 0.2\n")
+            .create();
+        let mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body(
+                "<html><body><a href=\"/private/\">private</a> <a href=\"/a\">a</a> <a href=\"/b\">b</a></body></html>",
+            )
+            .create();
+        let mock_private = server
+            .mock("GET", "/private/")
+            .with_status(200)
+            .expect(0)
+            .create();
+        let mock_pages = server
+            .mock("GET", mockito::Matcher::Regex(r"^/[ab]$".to_string()))
+            .with_status(200)
+            .with_body("<html><body>Page</body></html>")
+            .expect(2)
+            .create();
+
+        let rwl =
+            RecursiveWebLoader::new(server.url(), RecursiveWebLoaderOptions::default()).unwrap();
+        let start = std::time::Instant::now();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 3);
+        assert!(start.elapsed() >= Duration::from_millis(400));
+
+        mock_robots.assert();
+        mock_root.assert();
+        mock_private.assert();
+        mock_pages.assert();
+    }
+
+    #[tokio::test]
+    async fn load_caps_crawl_delay() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_robots = server
+            .mock("GET", "/robots.txt")
+            .with_status(200)
+            .with_body("User-agent: *\nCrawl-delay: 86400\n")
+            .create();
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body("<html><body><a href=\"/a\">a</a></body></html>")
+            .create();
+        let _mock_page = server
+            .mock("GET", "/a")
+            .with_status(200)
+            .with_body("<html><body>Page</body></html>")
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            max_crawl_delay: Some(200),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let start = std::time::Instant::now();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn load_root_disallowed_by_robots_txt() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_robots = server
+            .mock("GET", "/robots.txt")
+            .with_status(200)
+            .with_body("User-agent: test-bot\nDisallow: /\n")
+            .create();
+        let mock_root = server.mock("GET", "/").with_status(200).create();
+
+        let options = RecursiveWebLoaderOptions {
+            user_agent: Some("test-bot/1.0".to_string()),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let result = rwl.load().await;
+        assert!(matches!(result, Err(LoaderError::Disallowed { .. })));
+
+        let options = RecursiveWebLoaderOptions {
+            user_agent: Some("test-bot/1.0".to_string()),
+            respect_robots_txt: Some(false),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        assert!(rwl.load().await.is_ok());
+
+        mock_root.assert();
+    }
+
+    fn sitemap_body(server_url: &str, paths: &[&str]) -> String {
+        let urls: String = paths
+            .iter()
+            .map(|path| {
+                format!("<url><loc>{server_url}{path}</loc><lastmod>2024-05-01</lastmod></url>")
+            })
+            .collect();
+        format!("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">{urls}</urlset>")
+    }
+
+    #[tokio::test]
+    async fn load_only_sitemap_urls() {
+        let mut server = server_without_robots_txt().await;
+        let mock_root = server.mock("GET", "/").expect(0).create();
+        let mock_sitemap = server
+            .mock("GET", "/sitemap.xml")
+            .with_status(200)
+            .with_body(sitemap_body(&server.url(), &["/a", "/b/"]))
+            .create();
+        let mock_pages = server
+            .mock("GET", mockito::Matcher::Regex(r"^/(a|b/)$".to_string()))
+            .with_status(200)
+            .with_body("<html><body>Page <a href=\"/c\">c</a></body></html>")
+            .expect(2)
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            sitemap_mode: Some(SitemapMode::Only),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(result
+            .iter()
+            .all(|doc| doc.metadata.get("lastmod").unwrap() == "2024-05-01"));
+
+        mock_root.assert();
+        mock_sitemap.assert();
+        mock_pages.assert();
+    }
+
+    #[tokio::test]
+    async fn load_only_sitemap_urls_with_root() {
+        let mut server = server_without_robots_txt().await;
+        let mock_sitemap = server
+            .mock("GET", "/sitemap.xml")
+            .with_status(200)
+            .with_body(sitemap_body(&server.url(), &["/", "/a"]))
+            .create();
+        let mock_pages = server
+            .mock("GET", mockito::Matcher::Regex(r"^/a?$".to_string()))
+            .with_status(200)
+            .with_body("<html><body>Page</body></html>")
+            .expect(2)
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            sitemap_mode: Some(SitemapMode::Only),
+            requests_per_second: Some(5.0),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let start = std::time::Instant::now();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 2);
+        // the sitemap takes its turn too
+        assert!(start.elapsed() >= Duration::from_millis(400));
+
+        mock_sitemap.assert();
+        mock_pages.assert();
+    }
+
+    #[tokio::test]
+    async fn load_only_unreachable_sitemap() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_sitemap = server.mock("GET", "/sitemap.xml").with_status(503).create();
+
+        let options = RecursiveWebLoaderOptions {
+            sitemap_mode: Some(SitemapMode::Only),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        assert!(matches!(
+            rwl.load().await,
+            Err(LoaderError::Status { status, .. }) if status == StatusCode::SERVICE_UNAVAILABLE
+        ));
+
+        let _mock_robots = server.mock("GET", "/robots.txt").with_status(503).create();
+        let rwl = RecursiveWebLoader::new(
+            server.url(),
+            RecursiveWebLoaderOptions {
+                sitemap_mode: Some(SitemapMode::Only),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(matches!(
+            rwl.load().await,
+            Err(LoaderError::Disallowed { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn load_seeds_from_sitemap_index_in_robots_txt() {
+        let mut server = server_without_robots_txt().await;
+        let url = server.url();
+        let _mock_robots = server
+            .mock("GET", "/robots.txt")
+            .with_status(200)
+            .with_body(format!("Sitemap: {url}/sitemap-index.xml\n"))
+            .create();
+        let _mock_index = server
+            .mock("GET", "/sitemap-index.xml")
+            .with_status(200)
+            .with_body(format!(
+                "<sitemapindex><sitemap><loc>{url}/sitemap-docs.xml</loc></sitemap></sitemapindex>"
+            ))
+            .create();
+        let _mock_sitemap = server
+            .mock("GET", "/sitemap-docs.xml")
+            .with_status(200)
+            .with_body(sitemap_body(&url, &["/hidden"]))
+            .create();
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body(
+                "<html><body>Root <a href=\"/linked\">linked</a> <a href=\"/hidden\">hidden</a></body></html>",
+            )
+            .create();
+        let mock_pages = server
+            .mock(
+                "GET",
+                mockito::Matcher::Regex(r"^/(linked|hidden)$".to_string()),
+            )
+            .with_status(200)
+            .with_body("<html><body>Page</body></html>")
+            .expect(2)
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            sitemap_mode: Some(SitemapMode::Seed),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(url.clone(), options).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 3);
+        // linked from the root too, the page keeps its sitemap lastmod
+        let hidden = result
+            .iter()
+            .find(|doc| doc.metadata["source"] == format!("{url}/hidden"))
+            .unwrap();
+        assert_eq!(hidden.metadata.get_str("lastmod"), Some("2024-05-01"));
+
+        mock_pages.assert();
+    }
+
+    #[tokio::test]
+    async fn load_limits_requests_per_second() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body("<html><body><a href=\"/a\">a</a> <a href=\"/b\">b</a></body></html>")
+            .create();
+        let _mock_pages = server
+            .mock("GET", mockito::Matcher::Regex(r"^/[ab]$".to_string()))
+            .with_status(200)
+            .with_body("<html><body>Page</body></html>")
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            requests_per_second: Some(5.0),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let start = std::time::Instant::now();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 3);
+        assert!(start.elapsed() >= Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn load_throttles_head_requests() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_heads = server
+            .mock("HEAD", mockito::Matcher::Regex(r"^/a?$".to_string()))
+            .with_status(200)
+            .create();
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body("<html><body><a href=\"/a\">a</a></body></html>")
+            .create();
+        let _mock_page = server
+            .mock("GET", "/a")
+            .with_status(200)
+            .with_body("<html><body>Page</body></html>")
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            head_requests: Some(true),
+            requests_per_second: Some(5.0),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let start = std::time::Instant::now();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 2);
+        // four requests, three intervals apart
+        assert!(start.elapsed() >= Duration::from_millis(600));
+    }
+
+    #[tokio::test]
+    async fn load_backs_off_on_retry_after() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body("<html><body><a href=\"/busy\">busy</a> <a href=\"/a\">a</a></body></html>")
+            .create();
+        let _mock_busy = server
+            .mock("GET", "/busy")
+            .with_status(429)
+            .with_header("retry-after", "1")
+            .create();
+        let _mock_page = server
+            .mock("GET", "/a")
+            .with_status(200)
+            .with_body("<html><body>Page</body></html>")
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            max_concurrency: Some(1),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let start = std::time::Instant::now();
+        let results: Vec<_> = rwl.stream().collect().await;
+        assert!(matches!(results[1], Err(LoaderError::Status { .. })));
+        assert!(results[2].is_ok());
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn load_retries_transient_failures() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body(
+                "<html><body><a href=\"/flaky\">flaky</a> <a href=\"/gone\">gone</a></body></html>",
+            )
+            .create();
+        let mock_flaky_error = server
+            .mock("GET", "/flaky")
+            .with_status(503)
+            .with_header("content-type", "text/plain")
+            .expect(2)
+            .create();
+        // the third attempt succeeds
+        let mock_flaky = server
+            .mock("GET", "/flaky")
+            .with_status(200)
+            .with_body("<html><body>Flaky</body></html>")
+            .create();
+        let mock_gone = server
+            .mock("GET", "/gone")
+            .with_status(404)
+            .with_header("content-type", "text/plain")
+            .expect(1)
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            retry_policy: Some(RetryPolicy {
+                max_attempts: 3,
+                base_backoff: Duration::from_millis(10),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+
+        let (docs, report) = rwl.load_with_report().await.unwrap();
+
+        assert_eq!(docs.len(), 2);
+        assert_eq!(
+            report,
+            CrawlReport {
+                pages_loaded: 2,
+                pages_failed: 1,
+                retries: 2,
+            }
+        );
+
+        mock_flaky_error.assert();
+        mock_flaky.assert();
+        mock_gone.assert();
+    }
+
+    #[tokio::test]
+    async fn stream_applies_status_policy() {
+        let mut server = server_without_robots_txt().await;
+        mock_pages(
+            &mut server,
+            &[(
+                "/",
+                r#"<html><body><a href="/missing">missing</a></body></html>"#,
+            )],
+        )
+        .await;
+        server
+            .mock("GET", "/missing")
+            .with_status(404)
+            .with_body("<html><body>Page not found</body></html>")
+            .create_async()
+            .await;
+        let url = server.url();
+        let loader = |status_policy| {
+            let options = RecursiveWebLoaderOptions {
+                status_policy: Some(status_policy),
+                ..Default::default()
+            };
+            RecursiveWebLoader::new(url.clone(), options).unwrap()
+        };
+
+        let results: Vec<_> = loader(StatusPolicy::Report).stream().collect().await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().any(|result| matches!(
+            result,
+            Ok(doc) if doc.metadata.get_u64("status_code") == Some(200)
+        )));
+        assert!(results.iter().any(|result| matches!(
+            result,
+            Err(LoaderError::Status { status, .. }) if *status == StatusCode::NOT_FOUND
+        )));
+
+        let results: Vec<_> = loader(StatusPolicy::Skip).stream().collect().await;
+        assert_eq!(results.len(), 1);
+
+        let result = loader(StatusPolicy::Index).load().await.unwrap();
+        assert_eq!(result.len(), 2);
+        let missing_url = format!("{url}/missing");
+        let missing = result
+            .iter()
+            .find(|doc| doc.metadata.get_str("source") == Some(missing_url.as_str()))
+            .unwrap();
+        assert_eq!(missing.page_content, "Page not found");
+        assert_eq!(missing.metadata.get_u64("status_code"), Some(404));
+    }
+
+    #[tokio::test]
+    async fn load_skips_non_html_content() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_header("content-type", "text/html; charset=utf-8")
+            .with_body(
+                "<html><body><a href=\"/report\">report</a> <a href=\"/page\">page</a></body></html>",
+            )
+            .create();
+        let mock_report_head = server
+            .mock("HEAD", "/report")
+            .with_status(200)
+            .with_header("content-type", "application/pdf")
+            .create();
+        let mock_report = server
+            .mock("GET", "/report")
+            .with_status(200)
+            .with_header("content-type", "application/pdf")
+            .expect(0)
+            .create();
+        let _mock_page = server
+            .mock("GET", "/page")
+            .with_status(200)
+            .with_header("content-type", "application/xhtml+xml")
+            .with_body("<html><body>Page</body></html>")
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            head_requests: Some(true),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let results: Vec<_> = rwl.stream().collect().await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.is_ok()));
+
+        mock_report_head.assert();
+        mock_report.assert();
+    }
+
+    #[tokio::test]
+    async fn load_root_with_unsupported_content_type() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_header("content-type", "image/png")
+            .create();
+
+        let rwl =
+            RecursiveWebLoader::new(server.url(), RecursiveWebLoaderOptions::default()).unwrap();
+        assert!(matches!(
+            rwl.load().await,
+            Err(LoaderError::UnsupportedContentType { content_type, .. }) if content_type == "image/png"
+        ));
+
+        let options = RecursiveWebLoaderOptions {
+            allowed_mime_types: Some(vec!["text/html".to_string(), "image/png".to_string()]),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        assert!(rwl.load().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn load_root_error_page_with_any_content_type() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(503)
+            .with_header("content-type", "text/plain")
+            .create();
+
+        let rwl =
+            RecursiveWebLoader::new(server.url(), RecursiveWebLoaderOptions::default()).unwrap();
+        assert!(matches!(
+            rwl.load().await,
+            Err(LoaderError::Status { status, .. }) if status == StatusCode::SERVICE_UNAVAILABLE
+        ));
+    }
+
+    #[tokio::test]
+    async fn load_with_custom_extractor() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body("<html><body><h1>Title</h1><p>Body</p></body></html>")
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            extractor: Some(Box::new(|raw_html: &str, url: &str| {
+                let document = Html::parse_document(raw_html);
+                let selector = Selector::parse("h1").unwrap();
+                let title: String = document
+                    .select(&selector)
+                    .flat_map(|h1| h1.text())
+                    .collect();
+                format!("{title} ({url})")
+            })),
+            ..Default::default()
+        };
+        let url = server.url();
+        let rwl = RecursiveWebLoader::new(url.clone(), options).unwrap();
+        let result = rwl.load().await.unwrap();
+        // extractors get the url the page came from
+        assert_eq!(result[0].page_content, format!("Title ({url}/)"));
+    }
+
+    #[tokio::test]
+    async fn load_with_content_and_exclude_selectors() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(
+                r#"<html><body>
+                <div class="sidebar">Menu</div>
+                <div class="docs-content"><h1>Guide</h1><p>Install</p>
+                  <div id="footer">Edit this page</div></div>
+                <div class="docs-content"><p>Usage</p></div>
+                <div id="footer">Copyright</div>
+                </body></html>"#,
+            )
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            content_selectors: Some(vec!["div.docs-content".to_string()]),
+            exclude_selectors: Some(vec![".sidebar".to_string(), "#footer".to_string()]),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result[0].page_content.trim(), "Guide Install Usage");
+    }
+
+    #[test]
+    fn new_with_invalid_selector() {
+        let options = RecursiveWebLoaderOptions {
+            exclude_selectors: Some(vec!["div[".to_string()]),
+            ..Default::default()
+        };
+        let result = RecursiveWebLoader::new("https://example.com".to_string(), options);
+        assert!(matches!(
+            result,
+            Err(LoaderError::InvalidSelector { selector, .. }) if selector == "div["
+        ));
+    }
+
+    #[test]
+    fn new_with_invalid_user_agent() {
+        let options = RecursiveWebLoaderOptions {
+            user_agent: Some("bot\n1".to_string()),
+            ..Default::default()
+        };
+        let result = RecursiveWebLoader::new("https://example.com".to_string(), options);
+        assert!(matches!(
+            result,
+            Err(LoaderError::InvalidUserAgent { user_agent, .. }) if user_agent == "bot\n1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn load_follows_links_from_final_url() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(301)
+            .with_header("location", "/home/")
+            .create();
+        let _mock_home = server
+            .mock("GET", "/home/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(r#"<html><body><a href="page">Page</a></body></html>"#)
+            .create();
+        let mock_page = server
+            .mock("GET", "/home/page")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body("<html><body>Page</body></html>")
+            .expect(1)
+            .create();
+        let mock_wrong_page = server.mock("GET", "/page").expect(0).create();
+
+        let url = server.url();
+        let rwl =
+            RecursiveWebLoader::new(url.clone(), RecursiveWebLoaderOptions::default()).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result[0].metadata["source"], url);
+        assert_eq!(result[0].metadata["final_url"], format!("{url}/home/"));
+        assert_eq!(result[1].metadata["source"], format!("{url}/home/page"));
+        assert_eq!(result[0].metadata.get_u64("depth"), Some(0));
+        assert!(result[0].metadata.get_u64("fetched_at").is_some());
+        mock_page.assert();
+        mock_wrong_page.assert();
+    }
+
+    #[tokio::test]
+    async fn load_decodes_metadata_text() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(
+                r#"<html><head>
+                <title>
+                  AT&amp;T Docs &mdash;
+                  &quot;Caf&eacute;&quot; &#x26; <b>more</b>
+                </title>
+                <meta name="description" content="Tips &amp;
+                    tricks &lt;3">
+                <meta property="og:title" content="&#169; AT&amp;T">
+                </head><body>Content</body></html>"#,
+            )
+            .create();
+
+        let rwl =
+            RecursiveWebLoader::new(server.url(), RecursiveWebLoaderOptions::default()).unwrap();
+        let result = rwl.load().await.unwrap();
+        let metadata = &result[0].metadata;
+        assert_eq!(
+            metadata.get_str("title"),
+            Some("AT&T Docs — \"Café\" & <b>more</b>")
+        );
+        assert_eq!(metadata.get_str("description"), Some("Tips & tricks <3"));
+        assert_eq!(metadata.get_str("og:title"), Some("© AT&T"));
+    }
+
+    #[tokio::test]
+    async fn load_transcodes_declared_charset() {
+        let mut server = server_without_robots_txt().await;
+        let (shift_jis, _, _) = encoding_rs::SHIFT_JIS
+            .encode("<html><head><meta charset=\"shift_jis\"><title>日本語</title></head><body>こんにちは</body></html>");
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(shift_jis)
+            .create();
+
+        let rwl =
+            RecursiveWebLoader::new(server.url(), RecursiveWebLoaderOptions::default()).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result[0].page_content.trim(), "こんにちは");
+        assert_eq!(result[0].metadata.get_str("title"), Some("日本語"));
+        assert_eq!(result[0].metadata.get_str("encoding"), Some("Shift_JIS"));
+    }
+
+    #[tokio::test]
+    async fn load_deduplicates_normalized_urls() {
+        let mut server = server_without_robots_txt().await;
+        let url = server.url();
+        let upper_url = url.replace("http://", "HTTP://");
+        let _mock_root = server
+            .mock("GET", "/docs")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(format!(
+                r#"<html><body>
+                <a href="{url}/docs/">Root</a>
+                <a href="docs/a/">A</a>
+                <a href="docs/a">A</a>
+                <a href="docs/a/#section">A</a>
+                <a href="docs/a/?utm_source=x">A</a>
+                <a href="{upper_url}/docs/a/">A</a>
+                </body></html>"#
+            ))
+            .create();
+        let mock_root_dir = server
+            .mock("GET", "/docs/")
+            .with_status(200)
+            .expect(0)
+            .create();
+        let mock_a = server
+            .mock("GET", "/docs/a/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body("<html><body>A</body></html>")
+            .expect(1)
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            follow_policy: Some(FollowPolicy::AllHtml),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(format!("{url}/docs"), options).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 2);
+        mock_root_dir.assert();
+        mock_a.assert();
+    }
+
+    #[tokio::test]
+    async fn load_follows_directory_after_same_page_without_slash() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(r#"<html><body><a href="a">A</a> <a href="a/">A</a></body></html>"#)
+            .create();
+        let mock_a = server
+            .mock("GET", "/a")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(r#"<html><body><a href="a/child">Child</a></body></html>"#)
+            .expect(1)
+            .create();
+        let mock_a_dir = server
+            .mock("GET", "/a/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(r#"<html><body><a href="child">Child</a></body></html>"#)
+            .expect(1)
+            .create();
+        let mock_child = server
+            .mock("GET", "/a/child")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body("<html><body>Child</body></html>")
+            .expect(1)
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            max_concurrency: Some(1),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        assert_eq!(rwl.load().await.unwrap().len(), 4);
+        mock_a.assert();
+        mock_a_dir.assert();
+        mock_child.assert();
+    }
+
+    /// Mocks the html `pages` of a site, by path.
+    async fn mock_pages(server: &mut mockito::ServerGuard, pages: &[(&str, &str)]) {
+        for (path, body) in pages {
+            server
+                .mock("GET", *path)
+                .with_status(200)
+                .with_header("content-type", "text/html")
+                .with_body(*body)
+                .create_async()
+                .await;
+        }
+    }
+
+    /// The sources of `docs`, whatever order they were crawled in.
+    fn sources(docs: &[Document]) -> HashSet<&str> {
+        docs.iter()
+            .filter_map(|doc| doc.metadata.get_str("source"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn load_deduplicates_pages_by_content() {
+        let mut server = server_without_robots_txt().await;
+        let article = "<html><body><p>Install the crate, configure the loader and crawl \
+            your documentation site, every page comes back as a document.</p></body></html>";
+        mock_pages(
+            &mut server,
+            &[
+                (
+                    "/",
+                    r#"<html><body><a href="guide">Guide</a> <a href="guide?print=1">Print</a>
+                    <a href="other">Other</a></body></html>"#,
+                ),
+                ("/guide", article),
+                ("/guide?print=1", article),
+                ("/other", "<html><body>Another page</body></html>"),
+            ],
+        )
+        .await;
+        let url = server.url();
+        let guides = [format!("{url}/guide"), format!("{url}/guide?print=1")];
+
+        let options = RecursiveWebLoaderOptions {
+            dedup_policy: Some(DedupPolicy::default()),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(url.clone(), options).unwrap();
+        let result = rwl.load().await.unwrap();
+        let sources = sources(&result);
+        // whichever guide comes first is kept
+        assert_eq!(sources.len(), 3);
+        assert!(sources.contains(format!("{url}/other").as_str()));
+        assert_eq!(
+            guides
+                .iter()
+                .filter(|guide| sources.contains(guide.as_str()))
+                .count(),
+            1
+        );
+
+        let options = RecursiveWebLoaderOptions {
+            dedup_policy: Some(DedupPolicy {
+                action: DuplicateAction::Tag,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(url.clone(), options).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(result.len(), 4);
+        let tagged: Vec<_> = result
+            .iter()
+            .filter_map(|doc| doc.metadata.get_str("duplicate_of"))
+            .collect();
+        assert_eq!(tagged.len(), 1);
+        assert!(guides.iter().any(|guide| guide == tagged[0]));
+    }
+
+    fn canonical_document(source: &str, canonical: &str) -> Document {
+        let mut metadata = Metadata::new();
+        metadata.insert("source", source);
+        metadata.insert("canonical", canonical);
+        Document {
+            page_content: String::new(),
+            metadata,
+            structured_data: vec![],
+        }
+    }
+
+    #[test]
+    fn applies_canonical_policy() {
+        let url = "https://example.com/docs/";
+        let canonical_loader = |canonical_policy| {
+            let options = RecursiveWebLoaderOptions {
+                canonical_policy: Some(canonical_policy),
+                ..Default::default()
+            };
+            RecursiveWebLoader::new(url.to_string(), options).unwrap()
+        };
+        let page = "https://example.com/docs/a?ref=menu";
+        let canonical = "https://example.com/docs/a";
+
+        let rwl = canonical_loader(CanonicalPolicy::Source);
+        let mut doc = canonical_document(page, canonical);
+        rwl.apply_canonical_policy(&mut doc);
+        assert_eq!(doc.metadata.get_str("source"), Some(canonical));
+        assert_eq!(doc.metadata.get_str("fetched_url"), Some(page));
+        assert_eq!(rwl.canonical_key(&doc), Some(rwl.visit_key(canonical)));
+
+        // canonicals out of the crawl scope are not followed
+        let mut doc = canonical_document(page, "https://other.site/");
+        rwl.apply_canonical_policy(&mut doc);
+        assert_eq!(doc.metadata.get_str("source"), Some(page));
+        assert!(!doc.metadata.contains_key("fetched_url"));
+        assert_eq!(rwl.canonical_key(&doc), Some(rwl.visit_key(page)));
+
+        let rwl = canonical_loader(CanonicalPolicy::Separate);
+        let mut doc = canonical_document(page, canonical);
+        rwl.apply_canonical_policy(&mut doc);
+        assert_eq!(doc.metadata.get_str("source"), Some(page));
+        assert_eq!(rwl.canonical_key(&doc), Some(rwl.visit_key(canonical)));
+
+        let rwl = canonical_loader(CanonicalPolicy::Ignore);
+        let mut doc = canonical_document(page, canonical);
+        rwl.apply_canonical_policy(&mut doc);
+        assert_eq!(doc.metadata.get_str("source"), Some(page));
+        assert_eq!(rwl.canonical_key(&doc), None);
+    }
+
+    #[tokio::test]
+    async fn load_deduplicates_pages_by_canonical_url() {
+        let mut server = server_without_robots_txt().await;
+        let page = |canonical: &str| {
+            format!(
+                r#"<html><head><link rel="canonical" href="{canonical}"></head>
+                <body>{canonical}</body></html>"#
+            )
+        };
+        let (page_a, page_c) = (page("/a"), page("/c"));
+        mock_pages(
+            &mut server,
+            &[
+                (
+                    "/",
+                    r#"<html><body><a href="a">A</a> <a href="a?ref=menu">A</a>
+                    <a href="b">B</a> <a href="c">C</a></body></html>"#,
+                ),
+                ("/a", &page_a),
+                ("/a?ref=menu", &page_a),
+                ("/b", &page_c),
+                ("/c", &page_c),
+            ],
+        )
+        .await;
+        let url = server.url();
+
+        let rwl =
+            RecursiveWebLoader::new(url.clone(), RecursiveWebLoaderOptions::default()).unwrap();
+        let result = rwl.load().await.unwrap();
+        assert_eq!(
+            sources(&result),
+            HashSet::from([
+                url.as_str(),
+                format!("{url}/a").as_str(),
+                format!("{url}/c").as_str(),
+            ])
+        );
+
+        let options = RecursiveWebLoaderOptions {
+            canonical_policy: Some(CanonicalPolicy::Ignore),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(url.clone(), options).unwrap();
+        assert_eq!(rwl.load().await.unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn load_resolves_canonical_against_final_url() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(301)
+            .with_header("location", "/home/")
+            .create();
+        let _mock_home = server
+            .mock("GET", "/home/")
+            .with_status(200)
+            .with_body(
+                r#"<html><head><link rel="canonical" href="index"></head>
+                <body><a href="a">A</a> <a href="b">B</a></body></html>"#,
+            )
+            .create();
+        let _mock_pages = server
+            .mock("GET", mockito::Matcher::Regex(r"^/home/[ab]$".to_string()))
+            .with_status(200)
+            .with_body(
+                r#"<html><head><link rel="canonical" href="https://other.site/"></head>
+                <body>Page</body></html>"#,
+            )
+            .create();
+
+        let options = RecursiveWebLoaderOptions {
+            follow_policy: Some(FollowPolicy::AllHtml),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+        let result = rwl.load().await.unwrap();
+        let url = server.url();
+        let mut sources: Vec<_> = result
+            .iter()
+            .map(|doc| doc.metadata.get_str("source").unwrap())
+            .collect();
+        sources.sort();
+        // canonicals out of the crawl scope are not followed
+        assert_eq!(
+            sources,
+            [
+                format!("{url}/home/a"),
+                format!("{url}/home/b"),
+                format!("{url}/home/index"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn load_scopes_links_to_the_root() {
+        let mut server = server_without_robots_txt().await;
+        let page = |body: &str| format!("<html><body>{body}</body></html>");
+        let _mock_root = server
+            .mock("GET", "/docs/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(page(r#"<a href="a/">A</a>"#))
+            .create();
+        let _mock_a = server
+            .mock("GET", "/docs/a/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(page(r#"<a href="../b/">B</a> <a href="/blog/">Blog</a>"#))
+            .create();
+        let mock_b = server
+            .mock("GET", "/docs/b/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(page("B"))
+            .expect(2)
+            .create();
+        let mock_blog = server
+            .mock("GET", "/blog/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(page("Blog"))
+            .expect(1)
+            .create();
+
+        let url = format!("{}/docs/", server.url());
+        let rwl =
+            RecursiveWebLoader::new(url.clone(), RecursiveWebLoaderOptions::default()).unwrap();
+        assert_eq!(rwl.load().await.unwrap().len(), 3);
+
+        let options = RecursiveWebLoaderOptions {
+            scope: Some(CrawlScope::SameHost),
+            ..Default::default()
+        };
+        let rwl = RecursiveWebLoader::new(url, options).unwrap();
+        assert_eq!(rwl.load().await.unwrap().len(), 4);
+
+        mock_b.assert();
+        mock_blog.assert();
+    }
+
+    #[tokio::test]
+    async fn load_resolves_root_links_against_root_page() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/docs/intro")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(r#"<html><body><a href="setup">Setup</a></body></html>"#)
+            .create();
+        let mock_setup = server
+            .mock("GET", "/docs/setup")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body("<html><body>Setup</body></html>")
+            .expect(1)
+            .create();
+        let mock_wrong_setup = server.mock("GET", "/docs/intro/setup").expect(0).create();
+
+        let url = format!("{}/docs/intro", server.url());
+        let rwl = RecursiveWebLoader::new(url, RecursiveWebLoaderOptions::default()).unwrap();
+        assert_eq!(rwl.load().await.unwrap().len(), 2);
+        mock_setup.assert();
+        mock_wrong_setup.assert();
+    }
+
+    #[tokio::test]
+    async fn load_skips_redirects_out_of_scope() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/docs/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(r#"<html><body><a href="out/">Out</a></body></html>"#)
+            .create();
+        let _mock_out = server
+            .mock("GET", "/docs/out/")
+            .with_status(301)
+            .with_header("location", "/blog/")
+            .create();
+        let _mock_blog = server
+            .mock("GET", "/blog/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(r#"<html><body><a href="/docs/secret">Secret</a></body></html>"#)
+            .create();
+        let mock_secret = server.mock("GET", "/docs/secret").expect(0).create();
+
+        let url = format!("{}/docs/", server.url());
+        let rwl = RecursiveWebLoader::new(url, RecursiveWebLoaderOptions::default()).unwrap();
+        let (docs, report) = rwl.load_with_report().await.unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(report.pages_failed, 0);
+        mock_secret.assert();
+    }
+
+    #[tokio::test]
+    async fn load_disallowed_by_unreachable_robots_txt() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_robots = server.mock("GET", "/robots.txt").with_status(503).create();
+        let mock_root = server.mock("GET", "/").expect(0).create();
+
+        let rwl =
+            RecursiveWebLoader::new(server.url(), RecursiveWebLoaderOptions::default()).unwrap();
+        assert!(matches!(
+            rwl.load().await,
+            Err(LoaderError::Disallowed { .. })
+        ));
+        mock_root.assert();
+    }
+
+    #[tokio::test]
+    async fn load_scopes_links_to_the_redirected_root() {
+        let mut server = server_without_robots_txt().await;
+        let mut other_server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/docs/")
+            .with_status(301)
+            .with_header("location", &format!("{}/docs/", other_server.url()))
+            .create();
+        let _mock_other_root = other_server
+            .mock("GET", "/docs/")
+            .with_status(200)
+            .with_body(
+                r#"<html><body><a href="page/">Page</a> <a href="/blog/">Blog</a></body></html>"#,
+            )
+            .create();
+        let mock_page = other_server
+            .mock("GET", "/docs/page/")
+            .with_status(200)
+            .with_body("<html><body>Page</body></html>")
+            .expect(1)
+            .create();
+        let mock_blog = other_server.mock("GET", "/blog/").expect(0).create();
+
+        let url = format!("{}/docs/", server.url());
+        let rwl = RecursiveWebLoader::new(url, RecursiveWebLoaderOptions::default()).unwrap();
+        let docs = rwl.load().await.unwrap();
+        assert_eq!(docs.len(), 2);
+        mock_page.assert();
+        mock_blog.assert();
+    }
+
+    #[tokio::test]
+    async fn load_visits_redirect_targets_once() {
+        let mut server = server_without_robots_txt().await;
+        let _mock_root = server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body(r#"<html><body><a href="/old">Old</a> <a href="/new">New</a></body></html>"#)
+            .create();
+        let _mock_old = server
+            .mock("GET", "/old")
+            .with_status(301)
+            .with_header("location", "/new")
+            .create();
+        let _mock_new = server
+            .mock("GET", "/new")
+            .with_status(200)
+            .with_body("<html><body>New</body></html>")
+            .create();
+
+        for max_concurrency in [1, 4] {
+            let options = RecursiveWebLoaderOptions {
+                max_concurrency: Some(max_concurrency),
+                ..Default::default()
+            };
+            let rwl = RecursiveWebLoader::new(server.url(), options).unwrap();
+            let result = rwl.load().await.unwrap();
+            assert_eq!(result.len(), 2);
+        }
     }
 }
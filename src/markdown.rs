@@ -0,0 +1,491 @@
+use crate::extract::{normalize_whitespace, ContentExtractor, TextBlocklist};
+use reqwest::Url;
+use scraper::{ElementRef, Html, Node, Selector};
+
+/// Tags whose content is never part of the page text, besides the blocklisted
+/// ones.
+const SKIPPED_TAGS: &[&str] = &["head", "button", "select", "textarea"];
+
+const BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "ul",
+];
+
+/// Converts the `<body>` of a page to Markdown, keeping headings, emphasis,
+/// links, lists, tables and code blocks, but the blocklisted elements.
+#[derive(Clone, Debug, Default)]
+pub struct MarkdownExtractor {
+    pub blocklist: TextBlocklist,
+}
+
+/// Escapes the characters of `text` that Markdown would read as markup.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '[' | ']' | '#' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escapes a leading `-`, `+`, `>` or `N.` of `text`, which would start a
+/// list or a quote at the start of a line.
+fn escape_line_start(text: &str) -> String {
+    let trimmed = text.trim_start();
+    let leading = &text[..text.len() - trimmed.len()];
+    if trimmed.starts_with(['-', '+', '>']) {
+        return format!("{leading}\\{trimmed}");
+    }
+
+    let digits = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let rest = &trimmed[digits..];
+    if digits > 0 && rest.starts_with(['.', ')']) && (rest.len() == 1 || rest[1..].starts_with(' '))
+    {
+        return format!("{leading}{}\\{rest}", &trimmed[..digits]);
+    }
+    text.to_string()
+}
+
+/// Appends the text of a text node to an inline run, escaped.
+fn push_text(inline: &mut String, text: &str) {
+    let text = escape_text(&normalize_whitespace(text));
+    let before = inline.trim_end_matches(' ');
+    if before.is_empty() || before.ends_with('\n') {
+        push_inline(inline, &escape_line_start(&text));
+    } else {
+        push_inline(inline, &text);
+    }
+}
+
+/// Appends `fragment` to an inline run, without doubling the space between
+/// them.
+fn push_inline(inline: &mut String, fragment: &str) {
+    if inline.ends_with(' ') {
+        inline.push_str(fragment.strip_prefix(' ').unwrap_or(fragment));
+    } else {
+        inline.push_str(fragment);
+    }
+}
+
+/// Trims every line of an inline run.
+fn clean_inline(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Wraps the trimmed `inner` text in `marker`, keeping its surrounding
+/// whitespace outside of the markers.
+fn wrap(inner: &str, marker: &str) -> String {
+    let trimmed = inner.trim();
+    if trimmed.is_empty() {
+        return inner.to_string();
+    }
+
+    let leading = if inner.starts_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    let trailing = if inner.ends_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    format!("{leading}{marker}{trimmed}{marker}{trailing}")
+}
+
+/// The language of a code block, from a `language-*` or `lang-*` class.
+fn code_language(element: &ElementRef) -> Option<String> {
+    let code_selector = Selector::parse("code").unwrap();
+    std::iter::once(*element)
+        .chain(element.select(&code_selector))
+        .flat_map(|element| element.value().classes())
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+                .map(str::to_string)
+        })
+}
+
+struct Renderer<'a> {
+    base_url: Option<Url>,
+    blocklist: &'a TextBlocklist,
+}
+
+impl Renderer<'_> {
+    fn skips(&self, element: &ElementRef) -> bool {
+        SKIPPED_TAGS.contains(&element.value().name()) || self.blocklist.blocks(element)
+    }
+
+    fn resolve(&self, href: &str) -> String {
+        self.base_url
+            .as_ref()
+            .and_then(|base_url| base_url.join(href).ok())
+            .map_or_else(|| href.to_string(), |url| url.to_string())
+    }
+
+    /// Renders the children of a block container, blocks separated by
+    /// `separator`.
+    fn blocks(&self, element: &ElementRef, separator: &str) -> String {
+        let mut blocks = vec![];
+        let mut inline = String::new();
+
+        for node in element.children() {
+            match node.value() {
+                Node::Text(text) => push_text(&mut inline, text),
+                Node::Element(_) if self.skips(&ElementRef::wrap(node).unwrap()) => {}
+                Node::Element(child) if BLOCK_TAGS.contains(&child.name()) => {
+                    blocks.push(clean_inline(&inline));
+                    inline.clear();
+                    blocks.push(self.block(&ElementRef::wrap(node).unwrap()));
+                }
+                Node::Element(_) => push_inline(
+                    &mut inline,
+                    &self.inline_element(&ElementRef::wrap(node).unwrap()),
+                ),
+                _ => {}
+            }
+        }
+        blocks.push(clean_inline(&inline));
+
+        blocks
+            .into_iter()
+            .filter(|block| !block.is_empty())
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    fn block(&self, element: &ElementRef) -> String {
+        match element.value().name() {
+            name @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                let level = name[1..].parse().unwrap_or(1);
+                let title = clean_inline(&self.inline(element)).replace('\n', " ");
+                if title.is_empty() {
+                    String::new()
+                } else {
+                    format!("{} {title}", "#".repeat(level))
+                }
+            }
+            "p" => clean_inline(&self.inline(element)),
+            "hr" => "---".to_string(),
+            "pre" => self.code_block(element),
+            "blockquote" => self
+                .blocks(element, "\n\n")
+                .lines()
+                .map(|line| {
+                    if line.is_empty() {
+                        ">".to_string()
+                    } else {
+                        format!("> {line}")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "ul" => self.list(element, None),
+            "ol" => {
+                let start = element
+                    .value()
+                    .attr("start")
+                    .and_then(|start| start.parse().ok())
+                    .unwrap_or(1);
+                self.list(element, Some(start))
+            }
+            "table" => self.table(element),
+            _ => self.blocks(element, "\n\n"),
+        }
+    }
+
+    fn code_block(&self, element: &ElementRef) -> String {
+        let code: String = element.text().collect();
+        let code = code.strip_prefix('\n').unwrap_or(&code).trim_end();
+        let fence = if code.contains("```") { "````" } else { "```" };
+        let language = code_language(element).unwrap_or_default();
+        format!("{fence}{language}\n{code}\n{fence}")
+    }
+
+    fn list(&self, element: &ElementRef, start: Option<usize>) -> String {
+        element
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|child| child.value().name() == "li" && !self.skips(child))
+            .enumerate()
+            .map(|(index, item)| {
+                let marker = match start {
+                    Some(start) => format!("{}. ", start + index),
+                    None => "- ".to_string(),
+                };
+                let indent = " ".repeat(marker.len());
+                self.blocks(&item, "\n")
+                    .lines()
+                    .enumerate()
+                    .map(|(line_index, line)| match line_index {
+                        0 => format!("{marker}{line}"),
+                        _ if line.is_empty() => String::new(),
+                        _ => format!("{indent}{line}"),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn table(&self, element: &ElementRef) -> String {
+        let row_selector = Selector::parse("tr").unwrap();
+        let rows: Vec<Vec<String>> = element
+            .select(&row_selector)
+            .filter(|row| !self.skips(row))
+            .map(|row| {
+                row.children()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|cell| matches!(cell.value().name(), "th" | "td"))
+                    .map(|cell| {
+                        clean_inline(&self.inline(&cell))
+                            .replace('\n', " ")
+                            .replace('|', "\\|")
+                    })
+                    .collect()
+            })
+            .filter(|row: &Vec<String>| !row.is_empty())
+            .collect();
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return String::new();
+        }
+
+        let format_row = |row: &Vec<String>| {
+            let cells: Vec<&str> = (0..columns)
+                .map(|column| row.get(column).map_or("", String::as_str))
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        };
+        let mut lines = vec![
+            format_row(&rows[0]),
+            format!("|{}", " --- |".repeat(columns)),
+        ];
+        lines.extend(rows[1..].iter().map(format_row));
+        lines.join("\n")
+    }
+
+    fn inline(&self, element: &ElementRef) -> String {
+        let mut inline = String::new();
+        for node in element.children() {
+            match node.value() {
+                Node::Text(text) => push_text(&mut inline, text),
+                Node::Element(_) if self.skips(&ElementRef::wrap(node).unwrap()) => {}
+                Node::Element(child) if BLOCK_TAGS.contains(&child.name()) => {
+                    inline.push('\n');
+                    inline.push_str(&self.block(&ElementRef::wrap(node).unwrap()));
+                    inline.push('\n');
+                }
+                Node::Element(_) => push_inline(
+                    &mut inline,
+                    &self.inline_element(&ElementRef::wrap(node).unwrap()),
+                ),
+                _ => {}
+            }
+        }
+        inline
+    }
+
+    fn inline_element(&self, element: &ElementRef) -> String {
+        let value = element.value();
+        match value.name() {
+            _ if self.skips(element) => String::new(),
+            "br" => "\n".to_string(),
+            "strong" | "b" => wrap(&self.inline(element), "**"),
+            "em" | "i" => wrap(&self.inline(element), "*"),
+            "del" | "s" | "strike" => wrap(&self.inline(element), "~~"),
+            "code" | "kbd" | "samp" => {
+                let code: String = element.text().collect();
+                wrap(&normalize_whitespace(&code), "`")
+            }
+            "a" => {
+                let text = self.inline(element);
+                match value.attr("href") {
+                    Some(href) if !href.starts_with("javascript:") && !text.trim().is_empty() => {
+                        format!("[{}]({})", clean_inline(&text), self.resolve(href))
+                    }
+                    _ => text,
+                }
+            }
+            "img" => match value.attr("src") {
+                Some(src) => format!(
+                    "![{}]({})",
+                    escape_text(value.attr("alt").unwrap_or_default()),
+                    self.resolve(src)
+                ),
+                None => String::new(),
+            },
+            _ => self.inline(element),
+        }
+    }
+}
+
+impl ContentExtractor for MarkdownExtractor {
+    fn extract(&self, raw_html: &str, url: &str) -> String {
+        let document = Html::parse_document(raw_html);
+        let body_selector = Selector::parse("body").unwrap();
+        let renderer = Renderer {
+            base_url: Url::parse(url).ok(),
+            blocklist: &self.blocklist,
+        };
+
+        document
+            .select(&body_selector)
+            .map(|body| renderer.blocks(&body, "\n\n"))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_markdown(body: &str) -> String {
+        MarkdownExtractor::default().extract(
+            &format!("<html><head><title>Ignored</title></head><body>{body}</body></html>"),
+            "https://example.com/docs/",
+        )
+    }
+
+    #[test]
+    fn converts_headings_and_emphasis() {
+        let markdown = to_markdown(
+            "<h1>Guide</h1>
+            <p>Some <strong>bold</strong>, <em> italic </em> and <code>inline code</code>.</p>
+            <h3>Details</h3><hr>
+            <blockquote><p>Quoted</p><p>twice</p></blockquote>
+            <script>ignored()</script>",
+        );
+        assert_eq!(
+            markdown,
+            "# Guide\n\n\
+            Some **bold**, *italic* and `inline code`.\n\n\
+            ### Details\n\n\
+            ---\n\n\
+            > Quoted\n>\n> twice"
+        );
+    }
+
+    #[test]
+    fn converts_links_and_images() {
+        let markdown = to_markdown(
+            r#"<div>See <a href="intro">the intro</a>, <a href="https://other.org/">other</a>
+            <img src="/logo.png" alt="Logo"></div>"#,
+        );
+        assert_eq!(
+            markdown,
+            "See [the intro](https://example.com/docs/intro), [other](https://other.org/) \
+            ![Logo](https://example.com/logo.png)"
+        );
+    }
+
+    #[test]
+    fn converts_lists() {
+        let markdown = to_markdown(
+            "<ul>
+              <li>First</li>
+              <li>Second
+                <ol start=\"3\"><li>Third</li><li>Fourth</li></ol>
+              </li>
+            </ul>",
+        );
+        assert_eq!(markdown, "- First\n- Second\n  3. Third\n  4. Fourth");
+    }
+
+    #[test]
+    fn converts_tables() {
+        let markdown = to_markdown(
+            "<table>
+              <thead><tr><th>Name</th><th>Value</th></tr></thead>
+              <tbody><tr><td>a|b</td><td><b>1</b></td></tr><tr><td>c</td></tr></tbody>
+            </table>",
+        );
+        assert_eq!(
+            markdown,
+            "| Name | Value |\n| --- | --- |\n| a\\|b | **1** |\n| c |  |"
+        );
+    }
+
+    #[test]
+    fn skips_blocklisted_elements() {
+        let markdown = to_markdown(
+            r#"<p>Visible</p><p hidden>Hidden</p>
+            <p>Text <span aria-hidden="true">Decoration</span></p>
+            <div style="display: none">Collapsed</div>
+            <ul><li>Item</li><li hidden>Hidden item</li></ul>
+            <noscript>Enable JavaScript</noscript>"#,
+        );
+        assert_eq!(markdown, "Visible\n\nText\n\n- Item");
+    }
+
+    #[test]
+    fn escapes_markdown_syntax() {
+        let markdown = to_markdown(
+            "<p># not a heading</p><p>2 * 3 * 4</p><p>- not a list</p><p>1. not a list</p>
+            <p><a href=\"notes\">[1]</a> snake_case `tick`<br>+ 2</p>",
+        );
+        assert_eq!(
+            markdown,
+            "\\# not a heading\n\n\
+            2 \\* 3 \\* 4\n\n\
+            \\- not a list\n\n\
+            1\\. not a list\n\n\
+            [\\[1\\]](https://example.com/docs/notes) snake\\_case \\`tick\\`\n\\+ 2"
+        );
+    }
+
+    #[test]
+    fn converts_code_blocks() {
+        let markdown = to_markdown(
+            "<p>Run:</p><pre><code class=\"language-rust\">fn main() {\n    println!(\"hi\");\n}\n</code></pre>",
+        );
+        assert_eq!(
+            markdown,
+            "Run:\n\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```"
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use crate::extract::normalize_whitespace;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, ops::Index};
+use url::Url;
+
+/// The metadata of a `Document`, JSON values by key.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Metadata(Map<String, Value>);
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.0.get(key)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Value::as_str)
+    }
+
+    pub fn get_u64(&self, key: &str) -> Option<u64> {
+        self.get(key).and_then(Value::as_u64)
+    }
+
+    pub fn get_f64(&self, key: &str) -> Option<f64> {
+        self.get(key).and_then(Value::as_f64)
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(Value::as_bool)
+    }
+
+    pub fn get_array(&self, key: &str) -> Option<&Vec<Value>> {
+        self.get(key).and_then(Value::as_array)
+    }
+
+    /// Sets `key`, returning its previous value.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<Value>) -> Option<Value> {
+        self.0.insert(key.into(), value.into())
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.0.remove(key)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.0.iter()
+    }
+}
+
+impl Index<&str> for Metadata {
+    type Output = Value;
+
+    /// The value of `key`, `Value::Null` when missing.
+    fn index(&self, key: &str) -> &Value {
+        static NULL: Value = Value::Null;
+        self.0.get(key).unwrap_or(&NULL)
+    }
+}
+
+impl From<Map<String, Value>> for Metadata {
+    fn from(map: Map<String, Value>) -> Self {
+        Self(map)
+    }
+}
+
+impl From<Metadata> for Map<String, Value> {
+    fn from(metadata: Metadata) -> Self {
+        metadata.0
+    }
+}
+
+/// Meta tags, by `name` or `property`, recorded under their own name.
+const META_TAGS: &[&str] = &[
+    "description",
+    "author",
+    "keywords",
+    "og:title",
+    "og:image",
+    "og:type",
+    "article:published_time",
+    "article:modified_time",
+];
+
+/// Meta tag prefixes whose every tag is recorded under its own name.
+const META_PREFIXES: &[&str] = &["twitter:"];
+
+/// The metadata key of the meta tag `name`, custom mappings first.
+fn metadata_key(name: &str, custom_meta: &HashMap<String, String>) -> Option<String> {
+    if let Some(key) = custom_meta.get(name) {
+        return Some(key.clone());
+    }
+    if META_TAGS.contains(&name) || META_PREFIXES.iter().any(|prefix| name.starts_with(prefix)) {
+        return Some(name.to_string());
+    }
+    None
+}
+
+/// Extracts the metadata of the page at `url`. `custom_meta` maps extra meta
+/// tag names to the metadata keys they are recorded under.
+pub(crate) fn extract_metadata(
+    raw_html: &str,
+    url: &str,
+    custom_meta: &HashMap<String, String>,
+) -> Metadata {
+    let mut metadata = Metadata::new();
+    metadata.insert("source", url);
+
+    let document = Html::parse_document(raw_html);
+    let title_selector = Selector::parse("title").unwrap();
+    if let Some(title) = document.select(&title_selector).next() {
+        metadata.insert(
+            "title",
+            normalize_whitespace(&title.text().collect::<String>()).trim(),
+        );
+    }
+
+    // the first tag wins when a name is repeated, like `og:image`
+    let meta_selector = Selector::parse("meta[content]").unwrap();
+    for meta in document.select(&meta_selector) {
+        let value = meta.value();
+        let Some(name) = value.attr("property").or(value.attr("name")) else {
+            continue;
+        };
+        let name = name.trim().to_lowercase();
+        if let (Some(key), Some(content)) =
+            (metadata_key(&name, custom_meta), value.attr("content"))
+        {
+            if !metadata.contains_key(&key) {
+                metadata.insert(key, normalize_whitespace(content).trim());
+            }
+        }
+    }
+
+    let canonical_selector = Selector::parse("link[rel~=canonical][href]").unwrap();
+    if let Some(canonical) = document.select(&canonical_selector).next() {
+        let href = canonical.value().attr("href").unwrap_or_default();
+        if let Ok(canonical) = Url::parse(url).and_then(|url| url.join(href)) {
+            metadata.insert("canonical", canonical.to_string());
+        }
+    }
+
+    let html_selector = Selector::parse("html").unwrap();
+    if let Some(html) = document.select(&html_selector).next() {
+        if let Some(lang) = html.value().attr("lang") {
+            metadata.insert("language", lang.trim());
+        }
+    }
+
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html lang="en"><head>
+        <title>Guide</title>
+        <meta name="description" content="A guide">
+        <meta name="author" content="Jane Doe">
+        <meta name="keywords" content="rust, crawler">
+        <meta property="og:title" content="The guide">
+        <meta property="og:image" content="https://example.com/cover.png">
+        <meta property="og:image" content="https://example.com/other.png">
+        <meta property="og:type" content="article">
+        <meta property="article:published_time" content="2024-01-02T10:00:00Z">
+        <meta property="article:modified_time" content="2024-02-03T10:00:00Z">
+        <meta name="twitter:card" content="summary">
+        <meta name="twitter:site" content="@example">
+        <meta name="generator" content="Hugo">
+        <meta name="DC.creator" content="Docs team">
+        <link rel="canonical" href="/docs/guide/">
+        </head><body></body></html>"#;
+
+    #[test]
+    fn extracts_meta_tags() {
+        let metadata = extract_metadata(PAGE, "https://example.com/guide/?ref=x", &HashMap::new());
+
+        let expected = [
+            ("source", "https://example.com/guide/?ref=x"),
+            ("title", "Guide"),
+            ("description", "A guide"),
+            ("author", "Jane Doe"),
+            ("keywords", "rust, crawler"),
+            ("og:title", "The guide"),
+            ("og:image", "https://example.com/cover.png"),
+            ("og:type", "article"),
+            ("article:published_time", "2024-01-02T10:00:00Z"),
+            ("article:modified_time", "2024-02-03T10:00:00Z"),
+            ("twitter:card", "summary"),
+            ("twitter:site", "@example"),
+            ("canonical", "https://example.com/docs/guide/"),
+            ("language", "en"),
+        ];
+        assert_eq!(metadata.len(), expected.len());
+        for (key, value) in expected {
+            assert_eq!(metadata[key], value, "{key}");
+        }
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let mut metadata = Metadata::new();
+        metadata.insert("source", "https://example.com/");
+        metadata.insert("status_code", 200);
+        metadata.insert("links", vec!["https://example.com/a"]);
+        assert_eq!(metadata.get_u64("status_code"), Some(200));
+        assert_eq!(metadata.get_str("status_code"), None);
+        assert_eq!(metadata.get_array("links").map(Vec::len), Some(1));
+        assert_eq!(metadata["missing"], Value::Null);
+
+        let json = serde_json::to_string(&metadata).unwrap();
+        assert_eq!(serde_json::from_str::<Metadata>(&json).unwrap(), metadata);
+    }
+
+    #[test]
+    fn extracts_custom_meta_tags() {
+        let custom_meta = HashMap::from([
+            ("generator".to_string(), "generator".to_string()),
+            ("dc.creator".to_string(), "creator".to_string()),
+        ]);
+        let metadata = extract_metadata(PAGE, "https://example.com/", &custom_meta);
+        assert_eq!(metadata.get_str("generator"), Some("Hugo"));
+        assert_eq!(metadata["creator"], "Docs team");
+    }
+}
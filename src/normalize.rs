@@ -0,0 +1,190 @@
+use url::{form_urlencoded, Url};
+
+/// Normalizes crawled urls, so that the same page reached through different
+/// urls is only crawled once. Scheme and host case and default ports are
+/// always normalized by `Url` parsing.
+#[derive(Clone, Debug)]
+pub struct UrlNormalizer {
+    pub strip_fragment: bool,
+    /// Deduplicates urls whatever the order of their query parameters, the
+    /// fetched urls keep their order.
+    pub sort_query: bool,
+    /// Query parameters removed from urls, a trailing `*` matches any suffix.
+    pub tracking_params: Vec<String>,
+    /// Deduplicates `/a` and `/a/` as the same page, unless the follow policy
+    /// only follows the links of one of them, like the default
+    /// `FollowPolicy::DirectoriesOnly` does: both are then crawled, so the
+    /// links of `/a/` are not lost when `/a` comes first.
+    pub ignore_trailing_slash: bool,
+}
+
+impl Default for UrlNormalizer {
+    fn default() -> Self {
+        Self {
+            strip_fragment: true,
+            sort_query: true,
+            tracking_params: [
+                "utm_*", "gclid", "dclid", "fbclid", "msclkid", "yclid", "igshid", "mc_cid",
+                "mc_eid", "_ga", "_gl",
+            ]
+            .map(String::from)
+            .to_vec(),
+            ignore_trailing_slash: true,
+        }
+    }
+}
+
+impl UrlNormalizer {
+    fn is_tracking_param(&self, name: &str) -> bool {
+        self.tracking_params
+            .iter()
+            .any(|param| match param.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == param,
+            })
+    }
+
+    /// The raw query parameters of `url` but the tracking ones, with their
+    /// decoded names.
+    fn query_params<'a>(&self, url: &'a Url) -> Vec<(String, &'a str)> {
+        url.query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                let name = form_urlencoded::parse(param.as_bytes())
+                    .next()
+                    .map(|(name, _)| name.into_owned())
+                    .unwrap_or_default();
+                (name, param)
+            })
+            .filter(|(name, _)| !self.is_tracking_param(name))
+            .collect()
+    }
+
+    fn set_query(url: &mut Url, params: &[(String, &str)]) {
+        let query: Vec<&str> = params.iter().map(|(_, param)| *param).collect();
+        if query.is_empty() {
+            url.set_query(None);
+        } else {
+            url.set_query(Some(&query.join("&")));
+        }
+    }
+
+    /// The url pages are fetched from, the query parameters left but the
+    /// tracking ones are kept as is.
+    pub fn normalize(&self, url: &Url) -> Url {
+        let mut normalized = url.clone();
+        if self.strip_fragment {
+            normalized.set_fragment(None);
+        }
+
+        let params = self.query_params(url);
+        if url
+            .query()
+            .is_some_and(|query| query.split('&').count() != params.len())
+        {
+            Self::set_query(&mut normalized, &params);
+        }
+        normalized
+    }
+
+    /// The key pages are deduplicated by.
+    pub(crate) fn dedup_key(&self, url: &Url) -> String {
+        let mut key = self.normalize(url);
+        if self.sort_query {
+            // by name only, repeated parameters keep their order
+            let mut params = self.query_params(url);
+            params.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Self::set_query(&mut key, &params);
+        }
+        if self.ignore_trailing_slash && key.path().len() > 1 {
+            let path = key.path().trim_end_matches('/').to_string();
+            key.set_path(&path);
+        }
+        key.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(url: &str) -> String {
+        UrlNormalizer::default()
+            .normalize(&Url::parse(url).unwrap())
+            .to_string()
+    }
+
+    #[test]
+    fn normalizes_urls() {
+        assert_eq!(
+            normalize("HTTP://Example.COM:80/a?b=2&utm_source=x&a=1#section"),
+            "http://example.com/a?b=2&a=1"
+        );
+        assert_eq!(
+            normalize("https://example.com/a?flag&q=a%20b&c=2&c=1"),
+            "https://example.com/a?flag&q=a%20b&c=2&c=1"
+        );
+        assert_eq!(
+            normalize("https://example.com:443/a?utm_medium=y&fbclid=z"),
+            "https://example.com/a"
+        );
+        assert_eq!(
+            normalize("https://example.com:8443/a/?q=rust"),
+            "https://example.com:8443/a/?q=rust"
+        );
+    }
+
+    #[test]
+    fn keeps_configured_parts() {
+        let normalizer = UrlNormalizer {
+            strip_fragment: false,
+            sort_query: false,
+            tracking_params: vec!["ref".to_string()],
+            ignore_trailing_slash: false,
+        };
+        let url = Url::parse("https://example.com/a/?b=2&ref=x&a=1&utm_source=y#top").unwrap();
+        assert_eq!(
+            normalizer.normalize(&url).as_str(),
+            "https://example.com/a/?b=2&a=1&utm_source=y#top"
+        );
+        assert_eq!(
+            normalizer.dedup_key(&url),
+            "https://example.com/a/?b=2&a=1&utm_source=y#top"
+        );
+    }
+
+    #[test]
+    fn deduplicates_variants() {
+        let normalizer = UrlNormalizer::default();
+        let keys: Vec<String> = [
+            "https://example.com/a",
+            "https://example.com/a/",
+            "https://example.com/a#section",
+            "https://example.com/a?utm_source=x",
+            "HTTPS://EXAMPLE.com:443/a",
+        ]
+        .iter()
+        .map(|url| normalizer.dedup_key(&Url::parse(url).unwrap()))
+        .collect();
+        assert!(keys.iter().all(|key| key == "https://example.com/a"));
+
+        let key = |url| normalizer.dedup_key(&Url::parse(url).unwrap());
+        assert_eq!(
+            key("https://example.com/a?flag&c=2&b=%20&c=1"),
+            key("https://example.com/a?b=%20&c=2&flag&c=1")
+        );
+        assert_eq!(
+            key("https://example.com/a?flag&c=2&b=%20&c=1"),
+            "https://example.com/a?b=%20&c=2&c=1&flag"
+        );
+        assert_ne!(
+            key("https://example.com/a?c=2&c=1"),
+            key("https://example.com/a?c=1&c=2")
+        );
+
+        let root = normalizer.dedup_key(&Url::parse("https://example.com/").unwrap());
+        assert_eq!(root, "https://example.com/");
+    }
+}
@@ -0,0 +1,279 @@
+use crate::extract::{normalize_whitespace, ContentExtractor, TextBlocklist};
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use std::{collections::HashMap, sync::LazyLock};
+
+/// Tags that never hold main content, besides the blocklisted ones.
+const BOILERPLATE_TAGS: &[&str] = &[
+    "nav", "header", "footer", "aside", "form", "button", "select",
+];
+
+static NEGATIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)banner|breadcrumb|combx|comment|community|cookie|consent|disqus|extra|foot|header|legends|menu|modal|nav|popup|related|remark|share|shoutbox|sidebar|skyscraper|social|sponsor|toc|tool|widget|\bads?\b",
+    )
+    .unwrap()
+});
+
+static POSITIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)article|body|content|entry|hentry|main|page|post|story|text").unwrap()
+});
+
+/// Extracts the main content of a page, Mozilla Readability style: paragraphs
+/// are scored by their text, their scores flow up to their containers, and the
+/// best container, weighted down by its link density, is kept. Navigation,
+/// footers, sidebars, banners and the blocklisted elements are dropped.
+#[derive(Clone, Debug, Default)]
+pub struct MainContentExtractor {
+    pub blocklist: TextBlocklist,
+}
+
+fn class_and_id(element: &ElementRef) -> String {
+    let value = element.value();
+    format!(
+        "{} {}",
+        value.attr("class").unwrap_or_default(),
+        value.attr("id").unwrap_or_default()
+    )
+}
+
+/// Whether `element` looks like boilerplate, by its tag, role or class/id, or
+/// is blocklisted.
+fn is_boilerplate(element: &ElementRef, blocklist: &TextBlocklist) -> bool {
+    let value = element.value();
+    if BOILERPLATE_TAGS.contains(&value.name()) || blocklist.blocks(element) {
+        return true;
+    }
+    if matches!(
+        value.attr("role"),
+        Some("navigation" | "banner" | "contentinfo" | "complementary" | "dialog")
+    ) {
+        return true;
+    }
+    if matches!(value.name(), "body" | "main" | "article") {
+        return false;
+    }
+
+    let class_and_id = class_and_id(element);
+    NEGATIVE.is_match(&class_and_id) && !POSITIVE.is_match(&class_and_id)
+}
+
+fn class_weight(element: &ElementRef) -> f64 {
+    let class_and_id = class_and_id(element);
+    let mut weight = 0.0;
+    if NEGATIVE.is_match(&class_and_id) {
+        weight -= 25.0;
+    }
+    if POSITIVE.is_match(&class_and_id) {
+        weight += 25.0;
+    }
+    weight
+}
+
+fn initial_score(element: &ElementRef) -> f64 {
+    let tag_score = match element.value().name() {
+        "article" | "main" => 10.0,
+        "div" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    tag_score + class_weight(element)
+}
+
+fn text_len(element: &ElementRef) -> usize {
+    element.text().map(|text| text.trim().chars().count()).sum()
+}
+
+/// The share of the text of `element` inside links.
+fn link_density(element: &ElementRef) -> f64 {
+    let total_len = text_len(element);
+    if total_len == 0 {
+        return 0.0;
+    }
+
+    let link_selector = Selector::parse("a").unwrap();
+    let link_len: usize = element.select(&link_selector).map(|a| text_len(&a)).sum();
+    link_len as f64 / total_len as f64
+}
+
+fn has_boilerplate_ancestor(
+    element: &ElementRef,
+    root: &ElementRef,
+    blocklist: &TextBlocklist,
+) -> bool {
+    element
+        .ancestors()
+        .take_while(|ancestor| ancestor.id() != root.id())
+        .filter_map(ElementRef::wrap)
+        .any(|ancestor| is_boilerplate(&ancestor, blocklist))
+}
+
+/// Finds the element holding the main content below `root`.
+fn top_candidate<'a>(root: ElementRef<'a>, blocklist: &TextBlocklist) -> Option<ElementRef<'a>> {
+    let paragraph_selector = Selector::parse("p, pre, td").unwrap();
+    let mut scores = HashMap::new();
+
+    for paragraph in root.select(&paragraph_selector) {
+        if is_boilerplate(&paragraph, blocklist)
+            || has_boilerplate_ancestor(&paragraph, &root, blocklist)
+        {
+            continue;
+        }
+        let text: String = paragraph.text().collect();
+        let len = text.trim().chars().count();
+        if len < 25 {
+            continue;
+        }
+
+        let score = 1.0 + text.matches(',').count() as f64 + (len as f64 / 100.0).min(3.0);
+        let containers = paragraph.ancestors().filter_map(ElementRef::wrap).take(2);
+        for (level, container) in containers.enumerate() {
+            let entry = scores
+                .entry(container.id())
+                .or_insert_with(|| (container, initial_score(&container)));
+            entry.1 += if level == 0 { score } else { score / 2.0 };
+        }
+    }
+
+    scores
+        .into_values()
+        .map(|(element, score)| (element, score * (1.0 - link_density(&element))))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(element, _)| element)
+}
+
+/// The closest element holding all of `elements`, themselves included.
+fn common_ancestor<'a>(elements: &[ElementRef<'a>]) -> Option<ElementRef<'a>> {
+    let (first, others) = elements.split_first()?;
+    std::iter::once(*first)
+        .chain(first.ancestors().filter_map(ElementRef::wrap))
+        .find(|ancestor| {
+            others.iter().all(|other| {
+                other.id() == ancestor.id()
+                    || other.ancestors().any(|node| node.id() == ancestor.id())
+            })
+        })
+}
+
+fn collect_main_text(element: &ElementRef, blocklist: &TextBlocklist, text: &mut Vec<String>) {
+    for node in element.children() {
+        if let Some(child) = ElementRef::wrap(node) {
+            if is_boilerplate(&child, blocklist) {
+                continue;
+            }
+            // link lists such as inline menus or tag clouds
+            if matches!(child.value().name(), "ul" | "ol" | "div" | "section")
+                && link_density(&child) > 0.5
+            {
+                continue;
+            }
+            collect_main_text(&child, blocklist, text);
+        } else if let Some(node_text) = node.value().as_text() {
+            text.push(node_text.text.to_string());
+        }
+    }
+}
+
+impl ContentExtractor for MainContentExtractor {
+    fn extract(&self, raw_html: &str, _url: &str) -> String {
+        let document = Html::parse_document(raw_html);
+        let body_selector = Selector::parse("body").unwrap();
+        let Some(body) = document.select(&body_selector).next() else {
+            return String::new();
+        };
+
+        // pages marking their main content up are trusted, the others scored;
+        // several marked up blocks, such as a list of posts, are kept together
+        let main_selector = Selector::parse("article, main, [role=main]").unwrap();
+        let marked_up: Vec<_> = body
+            .select(&main_selector)
+            .filter(|main| !has_boilerplate_ancestor(main, &body, &self.blocklist))
+            .collect();
+        let main = common_ancestor(&marked_up)
+            .or_else(|| top_candidate(body, &self.blocklist))
+            .unwrap_or(body);
+
+        let mut text = Vec::new();
+        collect_main_text(&main, &self.blocklist, &mut text);
+        normalize_whitespace(&text.join(" ")).trim().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAGRAPH: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do \
+        eiusmod tempor incididunt ut labore et dolore magna aliqua.";
+
+    #[test]
+    fn keeps_article_and_drops_boilerplate() {
+        let html = format!(
+            r#"<html><body>
+            <nav><a href="/">Home</a> <a href="/docs">Docs</a></nav>
+            <div class="cookie-banner">We use cookies</div>
+            <article>
+              <h1>Guide <span aria-hidden="true">#</span></h1>
+              <p>{PARAGRAPH}</p>
+              <p hidden>Hidden</p>
+              <aside>Related pages</aside>
+              <div class="share"><a href="/x">Share</a></div>
+            </article>
+            <footer>Copyright</footer>
+            </body></html>"#
+        );
+
+        let text = MainContentExtractor::default().extract(&html, "https://example.com");
+        assert_eq!(text, format!("Guide {PARAGRAPH}"));
+    }
+
+    #[test]
+    fn keeps_every_article() {
+        let html = format!(
+            r#"<html><body>
+            <nav><a href="/">Home</a> <a href="/blog">Blog</a></nav>
+            <div class="posts">
+              <article><h2>First</h2><p>{PARAGRAPH}</p></article>
+              <article><h2>Second</h2><p>{PARAGRAPH}</p></article>
+            </div>
+            <aside><article><h2>Related</h2></article></aside>
+            <footer>Copyright</footer>
+            </body></html>"#
+        );
+
+        let text = MainContentExtractor::default().extract(&html, "https://example.com");
+        assert_eq!(text, format!("First {PARAGRAPH} Second {PARAGRAPH}"));
+    }
+
+    #[test]
+    fn scores_blocks_without_semantic_markup() {
+        let html = format!(
+            r#"<html><body>
+            <div id="menu"><ul><li><a href="/a">A page with a long enough title</a></li>
+              <li><a href="/b">Another page with a long enough title</a></li></ul></div>
+            <div class="sidebar"><p>{PARAGRAPH}</p></div>
+            <div class="content">
+              <h2>Install</h2>
+              <p>{PARAGRAPH}</p>
+              <p>{PARAGRAPH}</p>
+              <ul><li><a href="/1">one</a></li><li><a href="/2">two</a></li></ul>
+            </div>
+            <div class="footer">Legal notice</div>
+            </body></html>"#
+        );
+
+        let text = MainContentExtractor::default().extract(&html, "https://example.com");
+        assert_eq!(text, format!("Install {PARAGRAPH} {PARAGRAPH}"));
+    }
+
+    #[test]
+    fn falls_back_to_body() {
+        let text = MainContentExtractor::default().extract(
+            "<html><body><span>Short page</span></body></html>",
+            "https://example.com",
+        );
+        assert_eq!(text, "Short page");
+    }
+}
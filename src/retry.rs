@@ -0,0 +1,115 @@
+use crate::LoaderError;
+use reqwest::StatusCode;
+use std::time::Duration;
+
+/// When and how failed page fetches are retried.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Attempts per page, including the first one.
+    pub max_attempts: usize,
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Waits a random duration between half and all of the backoff.
+    pub jitter: bool,
+    pub retryable_statuses: Vec<StatusCode>,
+    pub retry_timeouts: bool,
+    pub retry_connection_errors: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+            retryable_statuses: vec![
+                StatusCode::REQUEST_TIMEOUT,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_timeouts: true,
+            retry_connection_errors: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub(crate) fn is_retryable(&self, err: &LoaderError) -> bool {
+        match err {
+            LoaderError::Status { status, .. } => self.retryable_statuses.contains(status),
+            LoaderError::Timeout { .. } => self.retry_timeouts,
+            LoaderError::Http { source, .. } => {
+                self.retry_connection_errors && (source.is_connect() || source.is_request())
+            }
+            _ => false,
+        }
+    }
+
+    /// The wait before retry number `retry`, starting at 0.
+    pub(crate) fn backoff(&self, retry: usize) -> Duration {
+        let factor = 2u32.saturating_pow(retry.try_into().unwrap_or(u32::MAX));
+        let backoff = self
+            .base_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+
+        if self.jitter {
+            backoff.mul_f64(0.5 + fastrand::f64() / 2.0)
+        } else {
+            backoff
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially() {
+        let policy = RetryPolicy {
+            base_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            jitter: false,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(3), Duration::from_millis(500));
+        assert_eq!(policy.backoff(64), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn jitters_backoff() {
+        let policy = RetryPolicy {
+            base_backoff: Duration::from_millis(100),
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            let backoff = policy.backoff(1);
+            assert!(backoff >= Duration::from_millis(100) && backoff <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn retries_configured_errors() {
+        let policy = RetryPolicy::default();
+        let status = |status| LoaderError::Status {
+            url: "https://example.com".to_string(),
+            status,
+        };
+        assert!(policy.is_retryable(&status(StatusCode::SERVICE_UNAVAILABLE)));
+        assert!(!policy.is_retryable(&status(StatusCode::NOT_FOUND)));
+        assert!(policy.is_retryable(&LoaderError::Timeout {
+            url: "https://example.com".to_string(),
+        }));
+        assert!(!policy.is_retryable(&LoaderError::Disallowed {
+            url: "https://example.com".to_string(),
+        }));
+    }
+}
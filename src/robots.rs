@@ -0,0 +1,226 @@
+use regex::Regex;
+use std::time::Duration;
+
+/// The most bytes of a robots.txt parsed, the rest is ignored (RFC 9309 2.5).
+pub(crate) const MAX_SIZE: usize = 500 * 1024;
+
+struct Rule {
+    allow: bool,
+    pattern: Regex,
+    specificity: usize,
+}
+
+struct Group {
+    user_agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+/// A parsed robots.txt file.
+#[derive(Default)]
+pub struct RobotsTxt {
+    groups: Vec<Group>,
+    sitemaps: Vec<String>,
+}
+
+fn compile_pattern(pattern: &str) -> Option<Regex> {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut re = String::from("^");
+    re.push_str(&regex::escape(pattern).replace(r"\*", ".*"));
+    if anchored {
+        re.push('$');
+    }
+    Regex::new(&re).ok()
+}
+
+/// Extracts the product token of a user agent, `my-bot/1.0 (+https://…)`
+/// gives `my-bot`.
+fn product_token(user_agent: &str) -> String {
+    user_agent
+        .split(|c: char| c == '/' || c.is_whitespace())
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+impl RobotsTxt {
+    /// The rules of an unreachable robots.txt, disallowing everything.
+    pub fn disallow_all() -> Self {
+        Self::parse("User-agent: *\nDisallow: /")
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut robots = RobotsTxt::default();
+        let mut in_agents = false;
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    if !in_agents || robots.groups.is_empty() {
+                        robots.groups.push(Group {
+                            user_agents: vec![],
+                            rules: vec![],
+                            crawl_delay: None,
+                        });
+                    }
+                    in_agents = true;
+                    if let Some(group) = robots.groups.last_mut() {
+                        group.user_agents.push(value.to_lowercase());
+                    }
+                }
+                "allow" | "disallow" => {
+                    in_agents = false;
+                    // an empty disallow allows everything
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let (Some(group), Some(pattern)) =
+                        (robots.groups.last_mut(), compile_pattern(value))
+                    else {
+                        continue;
+                    };
+                    group.rules.push(Rule {
+                        allow: key == "allow",
+                        pattern,
+                        specificity: value.len(),
+                    });
+                }
+                "crawl-delay" => {
+                    in_agents = false;
+                    let delay = value
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|delay| Duration::try_from_secs_f64(delay).ok());
+                    if let (Some(group), Some(delay)) = (robots.groups.last_mut(), delay) {
+                        group.crawl_delay = Some(delay);
+                    }
+                }
+                "sitemap" => robots.sitemaps.push(value.to_string()),
+                _ => {}
+            }
+        }
+
+        robots
+    }
+
+    /// Returns the groups that apply to `user_agent`: the ones naming its
+    /// product token, or the `*` ones when none does.
+    fn groups_for(&self, user_agent: &str) -> Vec<&Group> {
+        let token = product_token(user_agent);
+        let named: Vec<&Group> = self
+            .groups
+            .iter()
+            .filter(|group| group.user_agents.contains(&token))
+            .collect();
+        if !named.is_empty() {
+            return named;
+        }
+
+        self.groups
+            .iter()
+            .filter(|group| group.user_agents.iter().any(|agent| agent == "*"))
+            .collect()
+    }
+
+    /// Checks `path` (including its query) against the rules for
+    /// `user_agent`. The longest matching rule wins, allow wins ties.
+    pub fn is_allowed(&self, user_agent: &str, path: &str) -> bool {
+        if path == "/robots.txt" {
+            return true;
+        }
+
+        self.groups_for(user_agent)
+            .iter()
+            .flat_map(|group| group.rules.iter())
+            .filter(|rule| rule.pattern.is_match(path))
+            .max_by_key(|rule| (rule.specificity, rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+
+    pub fn crawl_delay(&self, user_agent: &str) -> Option<Duration> {
+        self.groups_for(user_agent)
+            .iter()
+            .find_map(|group| group.crawl_delay)
+    }
+
+    pub fn sitemaps(&self) -> &[String] {
+        &self.sitemaps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "
+User-agent: *
+Disallow: /private/
+Allow: /private/public
+Disallow: /*.pdf$
+Crawl-delay: 2
+
+# a dedicated group
+User-agent: other-bot
+User-agent: My-Bot
+Disallow: /
+Allow: /docs
+Crawl-delay: 0.5
+
+Sitemap: https://example.com/sitemap.xml
+";
+
+    #[test]
+    fn applies_wildcard_group() {
+        let robots = RobotsTxt::parse(ROBOTS);
+        assert!(robots.is_allowed("some-bot", "/"));
+        assert!(!robots.is_allowed("some-bot", "/private/data"));
+        assert!(robots.is_allowed("some-bot", "/private/public/data"));
+        assert!(!robots.is_allowed("some-bot", "/files/doc.pdf"));
+        assert!(robots.is_allowed("some-bot", "/files/doc.pdf?download=1"));
+        assert_eq!(robots.crawl_delay("some-bot"), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn applies_named_group() {
+        let robots = RobotsTxt::parse(ROBOTS);
+        assert!(!robots.is_allowed("my-bot/1.0 (+https://my-bot.dev)", "/"));
+        assert!(robots.is_allowed("my-bot/1.0", "/docs/intro"));
+        assert!(robots.is_allowed("my-bot/1.0", "/robots.txt"));
+        assert_eq!(
+            robots.crawl_delay("my-bot"),
+            Some(Duration::from_millis(500))
+        );
+    }
+
+    #[test]
+    fn collects_sitemaps() {
+        let robots = RobotsTxt::parse(ROBOTS);
+        assert_eq!(robots.sitemaps(), ["https://example.com/sitemap.xml"]);
+    }
+
+    #[test]
+    fn ignores_invalid_crawl_delays() {
+        for delay in ["1e30", "-1", "NaN", "soon"] {
+            let robots = RobotsTxt::parse(&format!("User-agent: *\nCrawl-delay: {delay}"));
+            assert_eq!(robots.crawl_delay("my-bot"), None);
+        }
+    }
+
+    #[test]
+    fn allows_everything_when_empty() {
+        let robots = RobotsTxt::parse("");
+        assert!(robots.is_allowed("my-bot", "/private/"));
+        assert_eq!(robots.crawl_delay("my-bot"), None);
+    }
+}
@@ -0,0 +1,184 @@
+use publicsuffix::{List, Psl};
+use std::sync::LazyLock;
+use url::{Host, Url};
+
+/// The public suffix list from https://publicsuffix.org, private domains like
+/// `github.io` included. `data/public_suffix_list.dat` is the list of
+/// 2023-02-09, refreshed with
+/// `curl -o data/public_suffix_list.dat https://publicsuffix.org/list/public_suffix_list.dat`.
+static PUBLIC_SUFFIXES: LazyLock<List> = LazyLock::new(|| {
+    include_str!("../data/public_suffix_list.dat")
+        .parse()
+        .expect("invalid public suffix list")
+});
+
+/// Which urls the crawl may go to, relative to the root url. Only http and
+/// https urls are ever in scope.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum CrawlScope {
+    /// Same scheme, host and port as the root.
+    SameOrigin,
+    /// Same host as the root, whatever the scheme and port.
+    SameHost,
+    /// Same registrable domain as the root, per the public suffix list, so
+    /// `docs.example.com` from `www.example.com` but not `bob.github.io` from
+    /// `alice.github.io`.
+    SameRegistrableDomain,
+    /// Same origin as the root, below its directory: the root path itself when
+    /// it ends with `/`, its parent directory otherwise.
+    #[default]
+    PathPrefix,
+    /// Hosts equal to, or subdomains of, one of these domains.
+    AllowedDomains(Vec<String>),
+    /// Any url.
+    Any,
+}
+
+/// The registrable domain of `host`, its public suffix and the label before,
+/// or `host` itself when it has none.
+fn registrable_domain(host: &str) -> &str {
+    match PUBLIC_SUFFIXES.domain(host.as_bytes()) {
+        Some(domain) => &host[host.len() - domain.as_bytes().len()..],
+        None => host,
+    }
+}
+
+fn is_domain_or_subdomain(host: &str, domain: &str) -> bool {
+    let domain = domain.trim_matches('.').to_lowercase();
+    host == domain
+        || host
+            .strip_suffix(&domain)
+            .is_some_and(|subdomain| subdomain.ends_with('.'))
+}
+
+impl CrawlScope {
+    /// Whether the crawl from `root` may go to `url`.
+    pub fn contains(&self, root: &Url, url: &Url) -> bool {
+        if !matches!(url.scheme(), "http" | "https") {
+            return false;
+        }
+        let host = url.host_str().unwrap_or_default();
+        match self {
+            CrawlScope::SameOrigin => url.origin() == root.origin(),
+            CrawlScope::SameHost => url.host() == root.host(),
+            CrawlScope::SameRegistrableDomain => match (url.host(), root.host()) {
+                (Some(Host::Domain(domain)), Some(Host::Domain(root_domain))) => {
+                    registrable_domain(domain) == registrable_domain(root_domain)
+                }
+                (host, root_host) => host == root_host,
+            },
+            CrawlScope::PathPrefix => {
+                let root_path = root.path();
+                let root_dir = &root_path[..=root_path.rfind('/').unwrap_or(0)];
+                url.origin() == root.origin() && url.path().starts_with(root_dir)
+            }
+            CrawlScope::AllowedDomains(domains) => domains
+                .iter()
+                .any(|domain| is_domain_or_subdomain(host, domain)),
+            CrawlScope::Any => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contains(scope: &CrawlScope, root: &str, url: &str) -> bool {
+        scope.contains(&Url::parse(root).unwrap(), &Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn finds_registrable_domains() {
+        assert_eq!(registrable_domain("www.example.com"), "example.com");
+        assert_eq!(registrable_domain("example.com"), "example.com");
+        assert_eq!(registrable_domain("a.b.example.co.uk"), "example.co.uk");
+        assert_eq!(registrable_domain("example.co.uk"), "example.co.uk");
+        assert_eq!(registrable_domain("docs.example.fr"), "example.fr");
+        assert_eq!(registrable_domain("alice.github.io"), "alice.github.io");
+        assert_eq!(registrable_domain("app.herokuapp.com"), "app.herokuapp.com");
+        assert_eq!(registrable_domain("localhost"), "localhost");
+    }
+
+    #[test]
+    fn scopes_by_origin_and_host() {
+        let root = "https://example.com/docs/";
+        let scope = CrawlScope::SameOrigin;
+        assert!(contains(&scope, root, "https://example.com/blog/"));
+        assert!(!contains(&scope, root, "http://example.com/docs/"));
+        assert!(!contains(
+            &scope,
+            root,
+            "https://example.com.evil.org/docs/"
+        ));
+
+        let scope = CrawlScope::SameHost;
+        assert!(contains(&scope, root, "http://example.com:8080/"));
+        assert!(!contains(&scope, root, "https://www.example.com/"));
+        assert!(!contains(&scope, root, "ftp://example.com/docs/"));
+    }
+
+    #[test]
+    fn scopes_by_registrable_domain() {
+        let scope = CrawlScope::SameRegistrableDomain;
+        let root = "https://www.example.co.uk/";
+        assert!(contains(&scope, root, "https://docs.example.co.uk/a"));
+        assert!(!contains(&scope, root, "https://other.co.uk/"));
+        assert!(!contains(&scope, root, "https://example.co.uk.evil.org/"));
+        assert!(!contains(
+            &scope,
+            "https://alice.github.io/",
+            "https://bob.github.io/"
+        ));
+        assert!(contains(
+            &scope,
+            "https://alice.github.io/",
+            "https://www.alice.github.io/"
+        ));
+        assert!(contains(
+            &scope,
+            "http://127.0.0.1:8000/",
+            "http://127.0.0.1:9000/"
+        ));
+    }
+
+    #[test]
+    fn scopes_by_root_path() {
+        let scope = CrawlScope::PathPrefix;
+        for root in [
+            "https://example.com/docs/",
+            "https://example.com/docs/intro",
+            "https://example.com/docs/guide.html",
+        ] {
+            assert!(contains(&scope, root, "https://example.com/docs/a/b"));
+            assert!(contains(&scope, root, "https://example.com/docs/setup"));
+            assert!(!contains(&scope, root, "https://example.com/blog/"));
+            assert!(!contains(
+                &scope,
+                root,
+                "https://example.com.evil.org/docs/"
+            ));
+        }
+        assert!(!contains(
+            &scope,
+            "https://example.com/docs/",
+            "https://example.com/docs-old/"
+        ));
+        assert!(contains(
+            &scope,
+            "https://example.com/docs",
+            "https://example.com/blog/"
+        ));
+    }
+
+    #[test]
+    fn scopes_by_allowed_domains() {
+        let scope =
+            CrawlScope::AllowedDomains(vec!["example.com".to_string(), "docs.rs".to_string()]);
+        let root = "https://example.com/";
+        assert!(contains(&scope, root, "https://api.example.com/"));
+        assert!(contains(&scope, root, "https://docs.rs/url"));
+        assert!(!contains(&scope, root, "https://notexample.com/"));
+        assert!(!contains(&scope, root, "https://example.com.evil.org/"));
+    }
+}
@@ -0,0 +1,169 @@
+use flate2::read::GzDecoder;
+use std::io::Read;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The most bytes a sitemap may take, compressed or not, as per the protocol.
+pub(crate) const MAX_SIZE: usize = 50 * 1024 * 1024;
+
+/// The most urls a sitemap may list, as per the protocol.
+pub(crate) const MAX_URLS: usize = 50_000;
+
+/// A `<url>` entry of a sitemap.
+#[derive(Debug, PartialEq)]
+pub struct SitemapEntry {
+    pub loc: String,
+    pub lastmod: Option<String>,
+    pub priority: Option<f32>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Sitemap {
+    UrlSet(Vec<SitemapEntry>),
+    /// A sitemap index, listing the locations of other sitemaps.
+    Index(Vec<String>),
+}
+
+/// Decompresses `body` when it is gzipped, sitemaps are often served as
+/// `sitemap.xml.gz`. Fails past `max_size` bytes.
+fn decode(body: &[u8], max_size: usize) -> Result<String, String> {
+    let xml = if body.starts_with(&GZIP_MAGIC) {
+        let mut xml = Vec::new();
+        GzDecoder::new(body)
+            .take(max_size as u64 + 1)
+            .read_to_end(&mut xml)
+            .map_err(|err| err.to_string())?;
+        xml
+    } else {
+        body.to_vec()
+    };
+    if xml.len() > max_size {
+        return Err(format!("sitemap larger than {max_size} bytes"));
+    }
+    String::from_utf8(xml).map_err(|err| err.to_string())
+}
+
+fn child_text<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|child| child.tag_name().name() == name)
+        .and_then(|child| child.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+impl Sitemap {
+    /// Parses a sitemap, keeping its first `MAX_URLS` urls.
+    pub fn parse(body: &[u8]) -> Result<Self, String> {
+        let xml = decode(body, MAX_SIZE)?;
+        let document = roxmltree::Document::parse(&xml).map_err(|err| err.to_string())?;
+        let root = document.root_element();
+
+        match root.tag_name().name() {
+            "urlset" => Ok(Sitemap::UrlSet(
+                root.children()
+                    .filter(|node| node.tag_name().name() == "url")
+                    .filter_map(|node| {
+                        Some(SitemapEntry {
+                            loc: child_text(node, "loc")?.to_string(),
+                            lastmod: child_text(node, "lastmod").map(str::to_string),
+                            priority: child_text(node, "priority")
+                                .and_then(|priority| priority.parse().ok()),
+                        })
+                    })
+                    .take(MAX_URLS)
+                    .collect(),
+            )),
+            "sitemapindex" => Ok(Sitemap::Index(
+                root.children()
+                    .filter(|node| node.tag_name().name() == "sitemap")
+                    .filter_map(|node| child_text(node, "loc").map(str::to_string))
+                    .take(MAX_URLS)
+                    .collect(),
+            )),
+            name => Err(format!("unexpected root element `{name}`")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    const URLSET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url>
+    <loc>https://example.com/docs/</loc>
+    <lastmod>2024-05-01</lastmod>
+    <priority>0.8</priority>
+  </url>
+  <url>
+    <loc> https://example.com/blog </loc>
+  </url>
+  <url>
+    <lastmod>2024-05-01</lastmod>
+  </url>
+</urlset>"#;
+
+    #[test]
+    fn parses_urlset() {
+        let sitemap = Sitemap::parse(URLSET.as_bytes()).unwrap();
+        assert_eq!(
+            sitemap,
+            Sitemap::UrlSet(vec![
+                SitemapEntry {
+                    loc: "https://example.com/docs/".to_string(),
+                    lastmod: Some("2024-05-01".to_string()),
+                    priority: Some(0.8),
+                },
+                SitemapEntry {
+                    loc: "https://example.com/blog".to_string(),
+                    lastmod: None,
+                    priority: None,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn parses_index() {
+        let index = r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap><loc>https://example.com/sitemap-docs.xml.gz</loc></sitemap>
+</sitemapindex>"#;
+        let sitemap = Sitemap::parse(index.as_bytes()).unwrap();
+        assert_eq!(
+            sitemap,
+            Sitemap::Index(vec!["https://example.com/sitemap-docs.xml.gz".to_string()])
+        );
+    }
+
+    #[test]
+    fn parses_gzipped() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(URLSET.as_bytes()).unwrap();
+        let body = encoder.finish().unwrap();
+
+        assert!(matches!(
+            Sitemap::parse(&body),
+            Ok(Sitemap::UrlSet(entries)) if entries.len() == 2
+        ));
+    }
+
+    #[test]
+    fn caps_decompressed_size() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&[b' '; 1024 * 1024]).unwrap();
+        let bomb = encoder.finish().unwrap();
+
+        assert!(decode(&bomb, 1024 * 1024).is_ok());
+        assert!(decode(&bomb, 1024 * 1024 - 1).is_err());
+        assert!(decode(&[b' '; 1024], 1023).is_err());
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(Sitemap::parse(b"<html><body>Not found</body></html>").is_err());
+        assert!(Sitemap::parse(b"not xml").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use crate::extract::normalize_whitespace;
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{Map, Value};
+
+/// The JSON-LD items and the microdata items of a page.
+pub(crate) fn extract_structured_data(raw_html: &str) -> Vec<Value> {
+    let document = Html::parse_document(raw_html);
+    let mut items = json_ld_items(&document);
+    items.extend(microdata_items(&document));
+    items
+}
+
+/// The items of the `application/ld+json` scripts, top level arrays and
+/// `@graph`s flattened. Invalid scripts are left out.
+fn json_ld_items(document: &Html) -> Vec<Value> {
+    let script_selector = Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+    let mut items = Vec::new();
+    for script in document.select(&script_selector) {
+        let text: String = script.text().collect();
+        let Ok(value) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        flatten_json_ld(value, &mut items);
+    }
+    items
+}
+
+fn flatten_json_ld(value: Value, items: &mut Vec<Value>) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                flatten_json_ld(value, items);
+            }
+        }
+        Value::Object(mut object) => match object.remove("@graph") {
+            Some(graph) => {
+                let context = object.get("@context").cloned();
+                let mut graph_items = Vec::new();
+                flatten_json_ld(graph, &mut graph_items);
+                for mut item in graph_items {
+                    if let (Some(context), Value::Object(item)) = (&context, &mut item) {
+                        item.entry("@context").or_insert_with(|| context.clone());
+                    }
+                    items.push(item);
+                }
+            }
+            None => items.push(Value::Object(object)),
+        },
+        _ => {}
+    }
+}
+
+/// The top level schema.org microdata items, as JSON-LD like objects.
+fn microdata_items(document: &Html) -> Vec<Value> {
+    let scope_selector = Selector::parse("[itemscope]:not([itemprop])").unwrap();
+    document
+        .select(&scope_selector)
+        .filter(|scope| {
+            !scope
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(|ancestor| ancestor.value().attr("itemscope").is_some())
+        })
+        .map(|scope| microdata_item(&scope))
+        .collect()
+}
+
+fn microdata_item(scope: &ElementRef) -> Value {
+    let mut item = Map::new();
+    if let Some(item_type) = scope.value().attr("itemtype") {
+        item.insert("@type".to_string(), Value::String(item_type.to_string()));
+    }
+    collect_properties(scope, &mut item);
+    Value::Object(item)
+}
+
+/// Adds the properties below `element`, without going into nested items.
+fn collect_properties(element: &ElementRef, item: &mut Map<String, Value>) {
+    for child in element.children().filter_map(ElementRef::wrap) {
+        let value = child.value();
+        let nested = value.attr("itemscope").is_some();
+        if let Some(names) = value.attr("itemprop") {
+            let property = if nested {
+                microdata_item(&child)
+            } else {
+                Value::String(property_value(&child))
+            };
+            for name in names.split_whitespace() {
+                match item.get_mut(name) {
+                    Some(Value::Array(values)) => values.push(property.clone()),
+                    Some(existing) => {
+                        *existing = Value::Array(vec![existing.take(), property.clone()])
+                    }
+                    None => {
+                        item.insert(name.to_string(), property.clone());
+                    }
+                }
+            }
+        }
+        if !nested {
+            collect_properties(&child, item);
+        }
+    }
+}
+
+fn property_value(element: &ElementRef) -> String {
+    let value = element.value();
+    let attr = match value.name() {
+        "meta" => value.attr("content"),
+        "a" | "area" | "link" => value.attr("href"),
+        "img" | "audio" | "video" | "source" | "iframe" | "embed" => value.attr("src"),
+        "object" => value.attr("data"),
+        "data" | "meter" => value.attr("value"),
+        "time" => value.attr("datetime"),
+        _ => None,
+    };
+    match attr {
+        Some(attr) => attr.to_string(),
+        None => normalize_whitespace(&element.text().collect::<String>())
+            .trim()
+            .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn extracts_json_ld() {
+        let html = r#"<html><head>
+            <script type="application/ld+json">
+            {"@context": "https://schema.org", "@graph": [
+              {"@type": "TechArticle", "headline": "Guide", "dateModified": "2024-02-03"},
+              {"@type": "BreadcrumbList", "itemListElement": [{"@type": "ListItem", "position": 1, "name": "Docs"}]}
+            ]}
+            </script>
+            <script type="application/ld+json">[{"@type": "Organization", "name": "Example"}]</script>
+            <script type="application/ld+json">{ not json</script>
+            </head><body></body></html>"#;
+
+        assert_eq!(
+            extract_structured_data(html),
+            vec![
+                json!({"@context": "https://schema.org", "@type": "TechArticle", "headline": "Guide", "dateModified": "2024-02-03"}),
+                json!({"@context": "https://schema.org", "@type": "BreadcrumbList", "itemListElement": [{"@type": "ListItem", "position": 1, "name": "Docs"}]}),
+                json!({"@type": "Organization", "name": "Example"}),
+            ]
+        );
+    }
+
+    #[test]
+    fn extracts_microdata() {
+        let html = r#"<html><body>
+            <div itemscope itemtype="https://schema.org/Product">
+              <h1 itemprop="name">Lamp</h1>
+              <img itemprop="image" src="https://example.com/lamp.png">
+              <span itemprop="keywords">desk</span> <span itemprop="keywords">light</span>
+              <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                <meta itemprop="priceCurrency" content="EUR"><span itemprop="price">25</span>
+              </div>
+            </div>
+            </body></html>"#;
+
+        assert_eq!(
+            extract_structured_data(html),
+            vec![json!({
+                "@type": "https://schema.org/Product",
+                "name": "Lamp",
+                "image": "https://example.com/lamp.png",
+                "keywords": ["desk", "light"],
+                "offers": {"@type": "https://schema.org/Offer", "priceCurrency": "EUR", "price": "25"},
+            })]
+        );
+    }
+}
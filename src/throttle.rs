@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+use tokio::time::Instant;
+
+/// Spaces requests to each host, hands out the next free time slot of a host
+/// to each request.
+pub struct HostThrottle {
+    min_interval: Duration,
+    /// The longest a host is held by a back-off.
+    max_delay: Duration,
+    next_slot: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostThrottle {
+    pub fn new(min_interval: Duration, max_delay: Duration) -> Self {
+        Self {
+            min_interval,
+            max_delay,
+            next_slot: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits for the next slot of `host`. The following request waits at least
+    /// `interval`, or the throttle minimum interval when longer.
+    pub async fn wait(&self, host: &str, interval: Duration) {
+        let interval = interval.max(self.min_interval);
+        let now = Instant::now();
+        let start = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let start = next_slot.get(host).map_or(now, |next| (*next).max(now));
+            let next = start.checked_add(interval).unwrap_or(start);
+            next_slot.insert(host.to_string(), next);
+            start
+        };
+        tokio::time::sleep_until(start).await;
+    }
+
+    /// Holds the requests to `host` for `delay`, at most the throttle maximum
+    /// delay.
+    pub fn back_off(&self, host: &str, delay: Duration) {
+        let Some(until) = Instant::now().checked_add(delay.min(self.max_delay)) else {
+            return;
+        };
+        let mut next_slot = self.next_slot.lock().unwrap();
+        let next = next_slot.entry(host.to_string()).or_insert(until);
+        *next = (*next).max(until);
+    }
+}
+
+/// Parses a `Retry-After` header value, either delay seconds or an HTTP date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_retry_after_seconds() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn parses_retry_after_date() {
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(30));
+        let delay = parse_retry_after(&date).unwrap();
+        assert!(delay > Duration::from_secs(25) && delay <= Duration::from_secs(30));
+
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn spaces_requests_per_host() {
+        let throttle = HostThrottle::new(Duration::from_millis(100), Duration::MAX);
+        let start = Instant::now();
+
+        throttle.wait("example.com", Duration::ZERO).await;
+        throttle.wait("other.com", Duration::ZERO).await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        throttle
+            .wait("example.com", Duration::from_millis(300))
+            .await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        throttle.wait("example.com", Duration::ZERO).await;
+        assert!(start.elapsed() >= Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn backs_off_host() {
+        let throttle = HostThrottle::new(Duration::ZERO, Duration::MAX);
+        let start = Instant::now();
+
+        throttle.back_off("example.com", Duration::from_millis(300));
+        throttle.wait("other.com", Duration::ZERO).await;
+        assert!(start.elapsed() < Duration::from_millis(300));
+        throttle.wait("example.com", Duration::ZERO).await;
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn caps_back_off() {
+        let throttle = HostThrottle::new(Duration::ZERO, Duration::from_millis(200));
+        let start = Instant::now();
+
+        let delay = parse_retry_after("18446744073709551615").unwrap();
+        throttle.back_off("example.com", delay);
+        throttle.wait("example.com", Duration::ZERO).await;
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}